@@ -0,0 +1,97 @@
+//! Spatial index scaling: one rebuild plus one sensor-range radius query,
+//! one k-nearest query and one lock-cone query per ship, at constant
+//! density, against all-pairs scans answering the same three queries.
+//!
+//! `cargo bench --bench spatial`
+
+use bevy::prelude::*;
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use space::faction::Faction;
+use space::spatial::{SpatialEntry, SpatialIndex};
+
+const SENSOR_RANGE: f32 = 100.0;
+const LOCK_CONE: f32 = 0.26;
+/// Roughly the density of the `basic` example's 100 ships.
+const VOLUME_PER_SHIP: f32 = 40_000.0;
+
+fn ships(count: usize) -> Vec<SpatialEntry> {
+    let mut rng = StdRng::seed_from_u64(7);
+    let half = (count as f32 * VOLUME_PER_SHIP).cbrt() / 2.0;
+    (0..count)
+        .map(|i| SpatialEntry {
+            entity: Entity::from_raw_u32(i as u32).unwrap(),
+            position: Vec3::new(
+                rng.gen_range(-half..half),
+                rng.gen_range(-half..half),
+                rng.gen_range(-half..half),
+            ),
+            faction: if i % 2 == 0 { Faction::Raiders } else { Faction::Player },
+        })
+        .collect()
+}
+
+fn spatial_index(c: &mut Criterion) {
+    let mut group = c.benchmark_group("spatial");
+    group.sample_size(20);
+    for count in [100, 1_000, 5_000, 10_000] {
+        let ships = ships(count);
+        let mut index = SpatialIndex::default();
+
+        group.bench_with_input(BenchmarkId::new("grid", count), &ships, |b, ships| {
+            b.iter(|| {
+                index.rebuild(ships.iter().copied());
+                let mut found = 0;
+                for ship in ships {
+                    found += index.within_radius(ship.position, SENSOR_RANGE).count();
+                    found += index
+                        .nearest(ship.position, 3, SENSOR_RANGE, |other| other.entity != ship.entity)
+                        .len();
+                    found += index
+                        .in_cone(ship.position, Vec3::NEG_Z, LOCK_CONE, SENSOR_RANGE)
+                        .count();
+                }
+                black_box(found)
+            });
+        });
+
+        // All-pairs is quadratic; stop before it takes minutes.
+        if count <= 5_000 {
+            group.bench_with_input(BenchmarkId::new("all_pairs", count), &ships, |b, ships| {
+                b.iter(|| {
+                    let mut found = 0;
+                    for ship in ships {
+                        found += ships
+                            .iter()
+                            .filter(|other| other.position.distance(ship.position) <= SENSOR_RANGE)
+                            .count();
+                        let mut nearest: Vec<f32> = ships
+                            .iter()
+                            .filter(|other| other.entity != ship.entity)
+                            .map(|other| other.position.distance(ship.position))
+                            .filter(|distance| *distance <= SENSOR_RANGE)
+                            .collect();
+                        nearest.sort_unstable_by(f32::total_cmp);
+                        nearest.truncate(3);
+                        found += nearest.len();
+                        found += ships
+                            .iter()
+                            .filter(|other| {
+                                let offset = other.position - ship.position;
+                                let distance = offset.length();
+                                (1e-3..=SENSOR_RANGE).contains(&distance)
+                                    && Vec3::NEG_Z.angle_between(offset / distance) <= LOCK_CONE
+                            })
+                            .count();
+                    }
+                    black_box(found)
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, spatial_index);
+criterion_main!(benches);
@@ -5,14 +5,26 @@ use bevy::picking::pointer::PointerInteraction;
 use bevy::prelude::*;
 use bevy_inspector_egui::bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use space::ai::{AiDifficulty, AiTarget, Difficulty, Perception, PilotSkill, Thinker};
 use space::combat::*;
 use space::common::{Enemy, MainCamera, Player};
 // use space::movement::MovementPlugin; // replaced by ControllerPlugin
 use space::controller::ControllerPlugin;
+use space::damage::Resistances;
+use space::faction::Faction;
+use space::hud::HudPlugin;
+use space::maneuver::{HeadOnScore, OnTailScore};
 use space::reticule::ReticulePlugin;
+use space::retreat::RallyPoint;
+use space::shields::{Shield, ShieldsPlugin};
+use space::squadron::{Formation, Squadron};
+use space::steering::{DesiredHeading, SteeringLimits, Wander};
+use space::subsystems::{SubsystemTarget, Subsystems, SubsystemsPlugin};
+use space::telemetry::MetricsPlugin;
+use space::tractor::{TractorBeam, TractorPlugin, TractorScore};
 use space::utils::generate_targets;
 use space::vfx::VfxPlugin;
-use space::weapons::{WeaponLoadout, WeaponsPlugin};
+use space::weapons::{FireInput, MissileLock, PointDefense, WeaponLoadout, WeaponsPlugin};
 
 use avian3d::prelude::*;
 
@@ -32,10 +44,15 @@ fn main() {
             PhysicsPlugins::default(),
             PhysicsPickingPlugin,
             ReticulePlugin,
+            HudPlugin,
             ControllerPlugin,
             WeaponsPlugin,
+            ShieldsPlugin,
+            SubsystemsPlugin,
+            TractorPlugin,
             VfxPlugin,
             CombatPlugin,
+            MetricsPlugin,
             EguiPlugin::default(),
             WorldInspectorPlugin::default()
         ))
@@ -47,6 +64,7 @@ fn main() {
             ..Default::default()
         })
         .insert_resource(Gravity(Vec3::ZERO))
+        .insert_resource(AiDifficulty(difficulty_from_args()))
         .add_systems(
             Startup,
             (
@@ -61,6 +79,22 @@ fn main() {
         .run();
 }
 
+/// `--difficulty rookie|regular|veteran|ace`; regular if absent.
+fn difficulty_from_args() -> Difficulty {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(name) = args
+        .iter()
+        .position(|arg| arg == "--difficulty")
+        .and_then(|index| args.get(index + 1))
+    else {
+        return Difficulty::default();
+    };
+    Difficulty::from_name(name).unwrap_or_else(|| {
+        warn!("Unknown difficulty {name:?}, using regular");
+        Difficulty::default()
+    })
+}
+
 fn spawn_camera(mut commands: Commands) {
     // ControllerPlugin takes over camera placement on the first PostUpdate.
     commands.spawn((MainCamera, Camera3d::default(), Transform::default()));
@@ -85,10 +119,21 @@ fn spawn_player(
         Player,
         RigidBody::Dynamic,
         ColliderConstructor::TrimeshFromMesh,
-        AiEnemy,
+        Faction::Player,
         WeaponLoadout::default(),
+        FireInput::default(),
+        MissileLock::default(),
+        SubsystemTarget::default(),
+        TractorBeam::default(),
         // Note: LinearVelocity + AngularVelocity are provided automatically by the Dynamic rigidbody.
-    ));
+    ))
+    .with_children(|ship| {
+        ship.spawn((
+            Name::new("Point Defense"),
+            Transform::from_xyz(0.0, 2.0, 0.0),
+            PointDefense::flak(),
+        ));
+    });
 }
 
 // Spawns n number of random targets
@@ -99,7 +144,7 @@ fn spawn_targets(
     player_query: Query<(Entity, &Transform), With<Player>>,
     assets: Res<AssetServer>,
 ) {
-    let mut spawn_cube = |position, color, name: String| {
+    let mut spawn_cube = |position, color, name: String, archetype: &str| {
         let material = materials.add(StandardMaterial {
             base_color: color,
             reflectance: 1.0,
@@ -116,7 +161,7 @@ fn spawn_targets(
         //     ..default()
         // };
 
-        let _enemy_entity = commands
+        commands
             .spawn((
                 Name::new(name),
                 Mesh3d(assets.load("models/spaceship.gltf#Mesh0/Primitive0")),
@@ -124,10 +169,29 @@ fn spawn_targets(
                 Transform::from_translation(position).with_scale(Vec3::new(0.1, 0.1, 0.5)),
                 Target,
                 AiMarker,
-                Ship { health: 100.0, max_health: 100.0, mass: 5.0 },
-                Thinker { threshold: 0.3, ..default() },
-                ThreatScore::default(),
-                RangeScore::default(),
+                Faction::Raiders,
+                (
+                    Ship { health: 100.0, max_health: 100.0, mass: 5.0 },
+                    Shield::directional(40.0, 10.0, 3.0),
+                    Resistances::light_fighter(),
+                    Subsystems::new(50.0),
+                    TractorBeam::default(),
+                    WeaponLoadout::default(),
+                    FireInput::default(),
+                ),
+                // Utility AI state (nested to stay under Bevy's bundle tuple limit).
+                (
+                    Thinker::new(archetype),
+                    AiTarget::default(),
+                    Perception::default(),
+                    PilotSkill::default(),
+                    ThreatScore::default(),
+                    RangeScore::default(),
+                    TractorScore::default(),
+                    HeadOnScore::default(),
+                    OnTailScore::default(),
+                ),
+                (SteeringLimits::default(), DesiredHeading::default(), Wander::default()),
                 Enemy::default(),
                 // Lightweight kinematic for scale (100s of enemies possible).
                 // Player stays full Dynamic + Trimesh for feel. See physics decision in todos.
@@ -192,7 +256,7 @@ fn spawn_targets(
             // })
             .observe(move |_over: On<Pointer<Over>>| {
                 info!("YOOO {name_clone}!");
-            }).id();
+            }).id()
 
         // commands
         //     .spawn((
@@ -222,13 +286,39 @@ fn spawn_targets(
         //     });
     };
 
-    for (position, color, name) in generate_targets(NUM_TARGETS) {
-        spawn_cube(
+    // The first half fly in squadrons of four, each led by an ace; of the
+    // rest, every fourth is a skirmisher.
+    let mut squadrons: Vec<Vec<Entity>> = Vec::new();
+    for (i, (position, color, name)) in generate_targets(NUM_TARGETS).into_iter().enumerate() {
+        let in_squadron = i < NUM_TARGETS / 2;
+        let archetype = match (in_squadron, i % 4 == 0) {
+            (true, true) => "ace",
+            (false, true) => "skirmisher",
+            (true, false) => "wingman",
+            (false, false) => "fighter",
+        };
+        let enemy = spawn_cube(
             position,
             color,
             name,
+            archetype,
         );
+        if in_squadron {
+            if i % 4 == 0 {
+                squadrons.push(Vec::new());
+            }
+            squadrons.last_mut().unwrap().push(enemy);
+        }
+    }
+    for (i, members) in squadrons.into_iter().enumerate() {
+        Squadron::spawn(&mut commands, Formation::ALL[i % Formation::ALL.len()], 10.0, members);
     }
+    // Where hurt raiders fall back to and patch themselves up.
+    commands.spawn((
+        Name::new("Raider rally point"),
+        RallyPoint { faction: Faction::Raiders },
+        Transform::from_xyz(0.0, 0.0, -150.0),
+    ));
 }
 
 // fn spawn_nameplate(mut commands: Commands) {
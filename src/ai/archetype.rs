@@ -0,0 +1,183 @@
+//! Per-archetype action sets, read from `assets/ai/archetypes.ron` when
+//! the app starts, so tuning them doesn't need a rebuild.
+//!
+//! An archetype lists the actions an agent may pick and how each is
+//! scored: a base utility plus weighted considerations, each reading one
+//! registered scorer's input through a `ResponseCurve`. Scorers and
+//! actions are referred to by their registered names; names the app
+//! doesn't know are reported once at startup and otherwise score zero or
+//! are never picked.
+
+use std::path::Path;
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::ResponseCurve;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Consideration {
+    /// Registered scorer name.
+    pub input: String,
+    #[serde(default)]
+    pub curve: ResponseCurve,
+    #[serde(default = "unit_weight")]
+    pub weight: f32,
+}
+
+fn unit_weight() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ActionConfig {
+    /// Registered action name.
+    pub action: String,
+    #[serde(default)]
+    pub base: f32,
+    #[serde(default)]
+    pub considerations: Vec<Consideration>,
+}
+
+impl ActionConfig {
+    /// `base` plus each consideration's weighted curve output, clamped to
+    /// `0..=1`. `input` looks a scorer's current value up by name.
+    pub fn score(&self, input: impl Fn(&str) -> f32) -> f32 {
+        let utility: f32 = self
+            .considerations
+            .iter()
+            .map(|consideration| {
+                consideration.weight * consideration.curve.evaluate(input(&consideration.input))
+            })
+            .sum();
+        (self.base + utility).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Archetype {
+    /// Actions scoring at or below this are never picked.
+    pub threshold: f32,
+    /// Bonus the current action gets when compared against the others.
+    #[serde(default)]
+    pub hysteresis: f32,
+    /// Seconds an agent sticks with a newly picked action before
+    /// reconsidering.
+    #[serde(default)]
+    pub min_commit_time: f32,
+    /// Picked when nothing clears the threshold.
+    pub default_action: String,
+    pub actions: Vec<ActionConfig>,
+}
+
+impl Archetype {
+    /// Index of the action to carry out given this frame's `scores` (one
+    /// per entry in `actions`), the action currently running, and how long
+    /// it has been running.
+    pub fn choose(&self, scores: &[f32], current: Option<usize>, running_for: f32) -> Option<usize> {
+        if let Some(current) = current
+            && running_for < self.min_commit_time
+        {
+            return Some(current);
+        }
+        let biased = |index: usize| {
+            scores[index] + if Some(index) == current { self.hysteresis } else { 0.0 }
+        };
+        (0..scores.len().min(self.actions.len()))
+            .filter(|&index| biased(index) > self.threshold)
+            .max_by(|&a, &b| biased(a).total_cmp(&biased(b)))
+            .or_else(|| {
+                self.actions
+                    .iter()
+                    .position(|action| action.action == self.default_action)
+            })
+    }
+}
+
+/// The archetypes file, relative to the assets folder.
+pub const ARCHETYPES_PATH: &str = "ai/archetypes.ron";
+
+/// Every archetype by name. Empty until `load_archetypes` reads
+/// `ARCHETYPES_PATH` at startup.
+#[derive(Resource, Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct AiArchetypes(pub HashMap<String, Archetype>);
+
+impl AiArchetypes {
+    pub fn parse(source: &str) -> Result<Self, ron::de::SpannedError> {
+        ron::from_str(source)
+    }
+
+    /// Reads and parses an archetypes file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BevyError> {
+        let source = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&source)?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Archetype> {
+        self.0.get(name)
+    }
+}
+
+/// Reads `ARCHETYPES_PATH` from the same assets folder the `AssetServer`
+/// uses. Archetypes the app inserted itself are left alone.
+pub(super) fn load_archetypes(mut archetypes: ResMut<AiArchetypes>) {
+    if !archetypes.0.is_empty() {
+        return;
+    }
+    let path = FileAssetReader::get_base_path().join("assets").join(ARCHETYPES_PATH);
+    match AiArchetypes::load(&path) {
+        Ok(loaded) => {
+            info!("Loaded {} AI archetypes from {}", loaded.0.len(), path.display());
+            *archetypes = loaded;
+        }
+        Err(error) => error!("Failed to load AI archetypes from {}: {error}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archetype() -> Archetype {
+        Archetype {
+            threshold: 0.3,
+            hysteresis: 0.1,
+            min_commit_time: 0.5,
+            default_action: "idle".into(),
+            actions: ["idle", "seek", "evade"]
+                .map(|action| ActionConfig {
+                    action: action.into(),
+                    base: 0.0,
+                    considerations: Vec::new(),
+                })
+                .to_vec(),
+        }
+    }
+
+    #[test]
+    fn bundled_archetypes_parse() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(ARCHETYPES_PATH);
+        let archetypes = AiArchetypes::load(path).unwrap();
+        let fighter = archetypes.get("fighter").unwrap();
+        assert!(fighter.actions.iter().any(|action| action.action == fighter.default_action));
+    }
+
+    #[test]
+    fn falls_back_to_default_below_threshold() {
+        assert_eq!(archetype().choose(&[0.0, 0.2, 0.1], None, 0.0), Some(0));
+        assert_eq!(archetype().choose(&[0.0, 0.5, 0.4], None, 0.0), Some(1));
+    }
+
+    #[test]
+    fn hysteresis_and_commitment_hold_the_current_action() {
+        let archetype = archetype();
+        // Evade edges ahead, but not by more than the hysteresis bonus.
+        assert_eq!(archetype.choose(&[0.0, 0.5, 0.55], Some(1), 1.0), Some(1));
+        assert_eq!(archetype.choose(&[0.0, 0.5, 0.7], Some(1), 1.0), Some(2));
+        // Too soon after switching to reconsider at all.
+        assert_eq!(archetype.choose(&[0.0, 0.0, 0.9], Some(1), 0.2), Some(1));
+    }
+}
@@ -0,0 +1,87 @@
+//! Response curves: how a raw consideration input in `0..=1` maps to the
+//! utility it contributes.
+
+use serde::Deserialize;
+
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+pub enum ResponseCurve {
+    /// `slope * x + intercept`.
+    Linear { slope: f32, intercept: f32 },
+    /// An S-curve centered on `midpoint`: near 0 below it, near 1 above.
+    /// Negative `steepness` flips it.
+    Logistic { steepness: f32, midpoint: f32 },
+    /// `scale * (x - center)² + intercept`: with a positive scale, slow to
+    /// start and then rising fast; negative makes a hump at `center`.
+    Quadratic { scale: f32, center: f32, intercept: f32 },
+}
+
+impl Default for ResponseCurve {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl ResponseCurve {
+    pub const IDENTITY: ResponseCurve = ResponseCurve::Linear {
+        slope: 1.0,
+        intercept: 0.0,
+    };
+
+    /// Input and output are both clamped to `0..=1`.
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        let y = match *self {
+            ResponseCurve::Linear { slope, intercept } => slope * x + intercept,
+            ResponseCurve::Logistic {
+                steepness,
+                midpoint,
+            } => 1.0 / (1.0 + (-steepness * (x - midpoint)).exp()),
+            ResponseCurve::Quadratic {
+                scale,
+                center,
+                intercept,
+            } => scale * (x - center).powi(2) + intercept,
+        };
+        y.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_stay_in_unit_range() {
+        let curves = [
+            ResponseCurve::Linear {
+                slope: 2.0,
+                intercept: -0.5,
+            },
+            ResponseCurve::Logistic {
+                steepness: 12.0,
+                midpoint: 0.5,
+            },
+            ResponseCurve::Quadratic {
+                scale: -4.0,
+                center: 0.5,
+                intercept: 1.0,
+            },
+        ];
+        for curve in curves {
+            for i in -2..=12 {
+                let y = curve.evaluate(i as f32 / 10.0);
+                assert!((0.0..=1.0).contains(&y), "{curve:?} gave {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn logistic_crosses_half_at_midpoint() {
+        let curve = ResponseCurve::Logistic {
+            steepness: 10.0,
+            midpoint: 0.3,
+        };
+        assert!((curve.evaluate(0.3) - 0.5).abs() < 1e-5);
+        assert!(curve.evaluate(0.1) < 0.2 && curve.evaluate(0.6) > 0.9);
+    }
+}
@@ -0,0 +1,394 @@
+//! Score history for the AI debug dashboard.
+//!
+//! `record_score_history` samples every agent's scorer inputs and action
+//! scores into a fixed-size ring buffer every `sample_interval`, and notes
+//! each action switch as it happens. `score_history_window` plots the
+//! selected agent's history against its archetype's threshold, with a
+//! marker at each switch. Pausing freezes recording so the plot can be
+//! panned and zoomed, and a cursor scrubbed along it to read off every
+//! score at that moment. An agent is selected from the dashboard list or by
+//! clicking its ship, through whichever picking backend the app has.
+
+use std::collections::VecDeque;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui};
+use egui_plot::{HLine, Legend, Line, LineStyle, Plot, VLine};
+
+use super::{AiArchetypes, AiRegistry, Thinker};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    pub time: f64,
+    /// Scorer values, indexed like `Thinker::inputs`.
+    pub inputs: Vec<f32>,
+    /// Action scores, indexed like the archetype's actions.
+    pub scores: Vec<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Switch {
+    pub time: f64,
+    pub action: &'static str,
+}
+
+/// One agent's ring buffer of samples and the switches within it.
+#[derive(Default, Debug)]
+pub struct AgentHistory {
+    samples: VecDeque<Sample>,
+    switches: VecDeque<Switch>,
+    /// The action last seen, once the agent has been observed at all.
+    action: Option<Option<&'static str>>,
+}
+
+impl AgentHistory {
+    pub fn samples(&self) -> &VecDeque<Sample> {
+        &self.samples
+    }
+
+    pub fn switches(&self) -> &VecDeque<Switch> {
+        &self.switches
+    }
+
+    /// Notes a switch if `action` isn't the one last seen. The first
+    /// observation only notes where the agent started.
+    pub fn observe(&mut self, time: f64, action: Option<&'static str>) {
+        let previous = self.action.replace(action);
+        if previous.is_some_and(|previous| previous != action)
+            && let Some(action) = action
+        {
+            self.switches.push_back(Switch { time, action });
+        }
+    }
+
+    /// Adds a sample, dropping the oldest (and any switches before it)
+    /// once `capacity` are kept.
+    pub fn push(&mut self, capacity: usize, sample: Sample) {
+        while self.samples.len() >= capacity.max(1) {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        let start = self.samples[0].time;
+        while self.switches.front().is_some_and(|switch| switch.time < start) {
+            self.switches.pop_front();
+        }
+    }
+
+    /// The latest sample at or before `time`.
+    pub fn at(&self, time: f64) -> Option<&Sample> {
+        let index = self.samples.partition_point(|sample| sample.time <= time);
+        self.samples.get(index.checked_sub(1)?)
+    }
+
+    /// First and last sample times.
+    pub fn span(&self) -> Option<(f64, f64)> {
+        Some((self.samples.front()?.time, self.samples.back()?.time))
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct ScoreHistory {
+    /// Seconds between samples.
+    pub sample_interval: f32,
+    /// Samples kept per agent.
+    pub capacity: usize,
+    /// Recording stops while paused, so the plot holds still to scrub.
+    pub paused: bool,
+    /// The agent the history window plots.
+    pub selected: Option<Entity>,
+    /// Where the scrub cursor sits while paused.
+    pub cursor: f64,
+    since_sample: f32,
+    agents: HashMap<Entity, AgentHistory>,
+}
+
+impl Default for ScoreHistory {
+    fn default() -> Self {
+        Self {
+            sample_interval: 0.05,
+            capacity: 600,
+            paused: false,
+            selected: None,
+            cursor: 0.0,
+            since_sample: 0.0,
+            agents: HashMap::default(),
+        }
+    }
+}
+
+impl ScoreHistory {
+    pub fn agent(&self, entity: Entity) -> Option<&AgentHistory> {
+        self.agents.get(&entity)
+    }
+
+    pub fn select(&mut self, entity: Entity) {
+        self.selected = Some(entity);
+    }
+
+    /// Pauses at the latest sample, or resumes recording.
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        if self.paused {
+            let latest = self
+                .selected
+                .and_then(|entity| self.agent(entity))
+                .and_then(AgentHistory::span);
+            self.cursor = latest.map_or(0.0, |(_, end)| end);
+        }
+    }
+
+    /// Seconds of history the buffers hold.
+    pub fn window(&self) -> f64 {
+        (self.capacity as f32 * self.sample_interval) as f64
+    }
+}
+
+pub(crate) fn record_score_history(
+    time: Res<Time>,
+    mut history: ResMut<ScoreHistory>,
+    mut removed: RemovedComponents<Thinker>,
+    agents: Query<(Entity, &Thinker)>,
+) {
+    // Despawned agents go even while paused, so their histories don't pile up.
+    for entity in removed.read() {
+        history.agents.remove(&entity);
+    }
+    if history.paused {
+        return;
+    }
+    let history = &mut *history;
+    let now = time.elapsed_secs_f64();
+    history.since_sample += time.delta_secs();
+    let sample = history.since_sample >= history.sample_interval;
+    if sample {
+        history.since_sample = 0.0;
+    }
+
+    for (entity, thinker) in &agents {
+        let agent = history.agents.entry(entity).or_default();
+        agent.observe(now, thinker.current_action);
+        if sample {
+            agent.push(
+                history.capacity,
+                Sample {
+                    time: now,
+                    inputs: thinker.inputs.clone(),
+                    scores: thinker.scores.clone(),
+                },
+            );
+        }
+    }
+}
+
+/// Selects an agent by clicking its ship.
+pub(crate) fn select_clicked_agent(
+    click: On<Pointer<Click>>,
+    agents: Query<(), With<Thinker>>,
+    mut history: ResMut<ScoreHistory>,
+) {
+    if click.event.button == PointerButton::Primary && agents.contains(click.entity) {
+        history.select(click.entity);
+    }
+}
+
+/// Rings the selected agent so it's easy to find in the scene.
+pub(crate) fn selected_agent_gizmo(
+    mut gizmos: Gizmos,
+    history: Res<ScoreHistory>,
+    agents: Query<&Transform, With<Thinker>>,
+) {
+    if let Some(transform) = history.selected.and_then(|entity| agents.get(entity).ok()) {
+        gizmos.sphere(transform.translation, 4.0, Color::WHITE);
+    }
+}
+
+/// A distinct colour for series `index` of `count`.
+fn series_color(index: usize, count: usize) -> egui::Color32 {
+    egui::ecolor::Hsva::new(index as f32 / count.max(1) as f32, 0.8, 0.9, 1.0).into()
+}
+
+pub(crate) fn score_history_window(
+    mut contexts: EguiContexts,
+    mut history: ResMut<ScoreHistory>,
+    registry: Res<AiRegistry>,
+    archetypes: Res<AiArchetypes>,
+    agents: Query<&Thinker>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    egui::Window::new("📈 Score History").show(ctx, |ui| {
+        let Some(entity) = history.selected else {
+            ui.label("Select an agent in the AI debug panel, or click its ship.");
+            return;
+        };
+        let Ok(thinker) = agents.get(entity) else {
+            history.selected = None;
+            return;
+        };
+        let Some(archetype) = archetypes.get(&thinker.archetype) else {
+            return;
+        };
+        ui.strong(format!(
+            "Entity #{} ({}): {}",
+            entity.index(),
+            thinker.archetype,
+            thinker.current_action.unwrap_or("-"),
+        ));
+
+        ui.horizontal(|ui| {
+            if ui.button(if history.paused { "▶ Resume" } else { "⏸ Pause" }).clicked() {
+                history.toggle_pause();
+            }
+            if ui.button("Deselect").clicked() {
+                history.selected = None;
+            }
+        });
+        let Some(agent) = history.agent(entity) else {
+            return;
+        };
+        let Some((start, end)) = agent.span() else {
+            ui.label("No samples yet.");
+            return;
+        };
+
+        let paused = history.paused;
+        let mut cursor = history.cursor.clamp(start, end);
+        if paused {
+            ui.add(egui::Slider::new(&mut cursor, start..=end).text("time (s)"));
+            if let Some(sample) = agent.at(cursor) {
+                ui.horizontal_wrapped(|ui| {
+                    for (name, value) in registry.scorers.iter().zip(&sample.inputs) {
+                        ui.label(format!("{name}: {value:.2}"));
+                    }
+                    for (action, score) in archetype.actions.iter().zip(&sample.scores) {
+                        ui.label(format!("{}: {score:.2}", action.action));
+                    }
+                });
+            }
+        }
+
+        let series = |value: &dyn Fn(&Sample) -> Option<f32>| -> Vec<[f64; 2]> {
+            agent
+                .samples()
+                .iter()
+                .filter_map(|sample| Some([sample.time, value(sample)? as f64]))
+                .collect()
+        };
+        let inputs: Vec<_> = registry
+            .scorers
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (*name, series(&|sample| sample.inputs.get(index).copied())))
+            .collect();
+        let actions: Vec<_> = archetype
+            .actions
+            .iter()
+            .enumerate()
+            .map(|(index, action)| (action.action.clone(), series(&|sample| sample.scores.get(index).copied())))
+            .collect();
+        let switches: Vec<Switch> = agent.switches().iter().copied().collect();
+        let threshold = archetype.threshold as f64;
+        let window = history.window();
+
+        let response = Plot::new(("score_history", entity))
+            .legend(Legend::default())
+            .height(240.0)
+            .include_y(0.0)
+            .include_y(1.0)
+            .allow_drag(paused)
+            .allow_zoom(paused)
+            .allow_scroll(paused)
+            .x_axis_label("time (s)")
+            .show(ui, |plot| {
+                if !paused {
+                    plot.set_plot_bounds_x((end - window).max(start)..=end);
+                }
+                // Scorer inputs dashed, so they read apart from action scores.
+                for (name, points) in inputs {
+                    plot.line(Line::new(name, points).style(LineStyle::dashed_dense()));
+                }
+                let count = actions.len();
+                let color_of = |name: &str| {
+                    let index = actions.iter().position(|(action, _)| action == name).unwrap_or(0);
+                    series_color(index, count)
+                };
+                for switch in &switches {
+                    plot.vline(
+                        VLine::new(switch.action, switch.time)
+                            .color(color_of(switch.action))
+                            .style(LineStyle::dotted_dense()),
+                    );
+                }
+                for (index, (name, points)) in actions.iter().enumerate() {
+                    plot.line(Line::new(name.clone(), points.clone()).color(series_color(index, count)).width(2.0));
+                }
+                plot.hline(HLine::new("threshold", threshold).color(egui::Color32::GRAY).style(LineStyle::dashed_loose()));
+                if paused {
+                    plot.vline(VLine::new("cursor", cursor).color(egui::Color32::WHITE));
+                }
+                // Clicking the plot moves the cursor there.
+                plot.response()
+                    .clicked()
+                    .then(|| plot.pointer_coordinate())
+                    .flatten()
+            });
+        if paused && let Some(clicked) = response.inner {
+            cursor = clicked.x.clamp(start, end);
+        }
+        history.cursor = cursor;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn sample(time: f64) -> Sample {
+        Sample {
+            time,
+            inputs: vec![time as f32],
+            scores: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn ring_buffer_keeps_the_latest_samples_and_their_switches() {
+        let mut history = AgentHistory::default();
+        // Where the agent started isn't a switch.
+        history.observe(0.0, Some("idle"));
+        history.observe(0.5, Some("idle"));
+        assert!(history.switches().is_empty());
+        history.observe(1.5, Some("seek"));
+        history.observe(2.5, Some("idle"));
+        assert_eq!(history.switches().len(), 2);
+        for step in 0..5 {
+            history.push(3, sample(step as f64));
+        }
+        assert_eq!(history.span(), Some((2.0, 4.0)));
+        // The switch to seek fell off the front with its samples.
+        assert_eq!(history.switches().iter().map(|switch| switch.action).collect::<Vec<_>>(), ["idle"]);
+        assert_eq!(history.at(3.5).map(|sample| sample.time), Some(3.0));
+        assert!(history.at(1.0).is_none());
+    }
+
+    #[test]
+    fn despawned_agents_are_forgotten() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<ScoreHistory>();
+        let gone = world.spawn(Thinker::default()).id();
+        let kept = world.spawn(Thinker::default()).id();
+        world.run_system_once(record_score_history).unwrap();
+        assert!(world.resource::<ScoreHistory>().agent(gone).is_some());
+
+        world.despawn(gone);
+        world.run_system_once(record_score_history).unwrap();
+        let history = world.resource::<ScoreHistory>();
+        assert!(history.agent(gone).is_none());
+        assert!(history.agent(kept).is_some());
+    }
+}
@@ -0,0 +1,239 @@
+//! AI level of detail.
+//!
+//! Sensing, scoring and picking an action are the expensive part of the
+//! AI, and an agent far from the player doesn't need to do them every
+//! frame. Each agent's `AiLod` puts it in a tier by its distance to the
+//! player (or the camera, once the player is gone), and each tier thinks at
+//! its own `AiLodSettings` interval. On top of that, `schedule_thinking`
+//! lets at most `think_budget` agents think in any one frame — nearest
+//! tier first, then whoever has waited longest — so a big fight spreads
+//! its thinking over several frames instead of spiking one. Getting shot or
+//! finishing an action jumps the queue.
+//!
+//! Movement still runs every frame for everyone, but `Far` agents skip the
+//! hazard terms (separation, mines, obstacle ray casts) nobody would see
+//! them get wrong.
+//!
+//! Systems that only run on thinking agents get the time since the agent
+//! last thought from `think_time` and should record how long they took
+//! with `AiLodStats::record`, which `publish_lod_metrics` reports per tier.
+//! Scorers that run in parallel record into a `Local<Parallel<AiLodStats>>`
+//! and `absorb` it once they're done.
+
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy::utils::Parallel;
+use metrics::gauge;
+
+use crate::combat::ShipDamaged;
+use crate::common::{MainCamera, Player};
+
+use super::ActionStatus;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Reflect)]
+pub enum AiTier {
+    #[default]
+    Near,
+    Mid,
+    Far,
+}
+
+impl AiTier {
+    pub const ALL: [AiTier; 3] = [AiTier::Near, AiTier::Mid, AiTier::Far];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AiTier::Near => "near",
+            AiTier::Mid => "mid",
+            AiTier::Far => "far",
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct AiLodSettings {
+    /// Agents within this distance of the player are `Near`.
+    pub near_distance: f32,
+    /// Agents beyond this distance are `Far`; those in between are `Mid`.
+    pub far_distance: f32,
+    /// How far past a boundary an agent must move to change tier.
+    pub hysteresis: f32,
+    /// Seconds between thinks, per tier; zero thinks every frame.
+    pub think_interval: [f32; 3],
+    /// Most agents that may think in one frame.
+    pub think_budget: usize,
+}
+
+impl Default for AiLodSettings {
+    fn default() -> Self {
+        Self {
+            near_distance: 120.0,
+            far_distance: 300.0,
+            hysteresis: 10.0,
+            think_interval: [0.0, 0.25, 1.0],
+            think_budget: 64,
+        }
+    }
+}
+
+impl AiLodSettings {
+    pub fn interval(&self, tier: AiTier) -> f32 {
+        self.think_interval[tier as usize]
+    }
+
+    /// The tier for an agent `distance` from the player, currently in
+    /// `current`.
+    pub fn tier(&self, current: AiTier, distance: f32) -> AiTier {
+        let tier_at = |distance: f32| {
+            if distance <= self.near_distance {
+                AiTier::Near
+            } else if distance <= self.far_distance {
+                AiTier::Mid
+            } else {
+                AiTier::Far
+            }
+        };
+        // Moving out takes going past the boundary by the hysteresis, as
+        // does moving in.
+        let outward = tier_at(distance - self.hysteresis);
+        let inward = tier_at(distance + self.hysteresis);
+        if outward > current {
+            outward
+        } else if inward < current {
+            inward
+        } else {
+            current
+        }
+    }
+}
+
+/// An agent's detail tier and thinking schedule. Required by `Thinker`.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct AiLod {
+    pub tier: AiTier,
+    /// Seconds since the agent last thought.
+    waiting: f32,
+    /// Whether the agent thinks this frame.
+    thinking: bool,
+    /// Seconds this frame's think covers.
+    elapsed: f32,
+}
+
+impl AiLod {
+    pub fn thinking(&self) -> bool {
+        self.thinking
+    }
+}
+
+/// Seconds since an agent last thought if it thinks this frame. Agents
+/// without an `AiLod` think every frame.
+pub(crate) fn think_time(lod: Option<&AiLod>, dt: f32) -> Option<f32> {
+    match lod {
+        Some(lod) => lod.thinking.then_some(lod.elapsed),
+        None => Some(dt),
+    }
+}
+
+/// This frame's per-tier numbers, reported by `publish_lod_metrics`.
+#[derive(Resource, Default, Debug)]
+pub struct AiLodStats {
+    pub agents: [usize; 3],
+    pub thinking: [usize; 3],
+    /// Agents due to think that the budget held over to a later frame.
+    pub deferred: usize,
+    pub think_time: [Duration; 3],
+}
+
+impl AiLodStats {
+    /// Adds the time since `started` to `tier`'s thinking time.
+    pub fn record(&mut self, tier: AiTier, started: Instant) {
+        self.think_time[tier as usize] += started.elapsed();
+    }
+
+    /// Adds the think times a parallel system recorded per thread, and
+    /// clears them for its next run.
+    pub fn absorb(&mut self, locals: &mut Parallel<AiLodStats>) {
+        for local in locals.iter_mut() {
+            for (total, time) in self.think_time.iter_mut().zip(&mut local.think_time) {
+                *total += std::mem::take(time);
+            }
+        }
+    }
+}
+
+/// Re-tiers every agent and picks who thinks this frame.
+#[allow(clippy::type_complexity)]
+pub(crate) fn schedule_thinking(
+    time: Res<Time>,
+    settings: Res<AiLodSettings>,
+    mut stats: ResMut<AiLodStats>,
+    mut damaged: MessageReader<ShipDamaged>,
+    focus: Query<(&Transform, Has<Player>), Or<(With<Player>, With<MainCamera>)>>,
+    mut agents: Query<(Entity, &Transform, &mut AiLod, Option<&ActionStatus>)>,
+) {
+    let dt = time.delta_secs();
+    *stats = AiLodStats::default();
+    // Prefer the player; the camera stands in once it's gone.
+    let focus = focus
+        .iter()
+        .max_by_key(|(_, player)| *player)
+        .map(|(transform, _)| transform.translation);
+
+    let shot: Vec<Entity> = damaged.read().map(|event| event.ship).collect();
+
+    let mut due = Vec::new();
+    for (entity, transform, mut lod, status) in &mut agents {
+        let distance = focus.map_or(0.0, |focus| focus.distance(transform.translation));
+        lod.tier = settings.tier(lod.tier, distance);
+        lod.waiting += dt;
+        lod.thinking = false;
+        stats.agents[lod.tier as usize] += 1;
+        // A finished action leaves the agent with nothing to do, and
+        // getting shot should get a reaction.
+        let urgent = status == Some(&ActionStatus::Finished) || shot.contains(&entity);
+        if urgent || lod.waiting >= settings.interval(lod.tier) {
+            due.push((!urgent, lod.tier, lod.waiting, entity));
+        }
+    }
+
+    due.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)).then(b.2.total_cmp(&a.2)));
+    stats.deferred = due.len().saturating_sub(settings.think_budget);
+    due.truncate(settings.think_budget);
+    let mut thinkers = agents.iter_many_mut(due.iter().map(|(_, _, _, entity)| *entity));
+    while let Some((_, _, mut lod, _)) = thinkers.fetch_next() {
+        lod.thinking = true;
+        lod.elapsed = lod.waiting;
+        lod.waiting = 0.0;
+        stats.thinking[lod.tier as usize] += 1;
+    }
+}
+
+pub(crate) fn publish_lod_metrics(stats: Res<AiLodStats>) {
+    for tier in AiTier::ALL {
+        let index = tier as usize;
+        gauge!("ai.lod.agents", "tier" => tier.name()).set(stats.agents[index] as f64);
+        gauge!("ai.lod.thinking", "tier" => tier.name()).set(stats.thinking[index] as f64);
+        gauge!("ai.lod.think_us", "tier" => tier.name()).set(stats.think_time[index].as_secs_f64() * 1e6);
+    }
+    gauge!("ai.lod.deferred").set(stats.deferred as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers_follow_distance_with_hysteresis() {
+        let settings = AiLodSettings::default();
+        assert_eq!(settings.tier(AiTier::Near, 50.0), AiTier::Near);
+        assert_eq!(settings.tier(AiTier::Near, 500.0), AiTier::Far);
+        assert_eq!(settings.tier(AiTier::Far, 10.0), AiTier::Near);
+        // Just past a boundary isn't far enough to switch either way.
+        assert_eq!(settings.tier(AiTier::Near, settings.near_distance + 5.0), AiTier::Near);
+        assert_eq!(settings.tier(AiTier::Mid, settings.near_distance - 5.0), AiTier::Mid);
+        assert_eq!(settings.tier(AiTier::Near, settings.near_distance + 15.0), AiTier::Mid);
+    }
+}
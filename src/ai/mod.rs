@@ -0,0 +1,372 @@
+//! Utility AI framework.
+//!
+//! Scorers and actions are plain components registered by type:
+//!
+//! - A `Scorer` holds a value in `0..=1` that its own system keeps up to
+//!   date in `AiSet::Scorers`. `register_ai_scorer` copies it into
+//!   `Thinker::inputs` every frame under the scorer's `NAME`.
+//! - An `Action` is a marker component. `pick_actions` inserts the picked
+//!   action's marker and removes the previous one; the systems that carry
+//!   actions out just filter on `With<A>` in `AiSet::Actions`.
+//!
+//! Which actions an agent weighs, and how, comes from its `Archetype` in
+//! `AiArchetypes`, read from `assets/ai/archetypes.ron` at startup. An
+//! agent whose archetype isn't there is reported when it spawns and never
+//! acts. To stop agents flip-flopping between two close scores, the
+//! running action gets a `hysteresis` bonus and can't be replaced until it
+//! has run for `min_commit_time`.
+//!
+//! An action that must run to completion, like a dogfight maneuver, puts
+//! `ActionStatus::Running` on the agent; the picker leaves it alone until
+//! the action switches that to `Finished`, then picks afresh — possibly the
+//! same action again, which is re-inserted so it starts over.
+//!
+//! Agents far from the player think less often; see `lod`.
+
+mod archetype;
+mod curve;
+mod history;
+mod lod;
+mod perception;
+mod skill;
+mod targeting;
+
+pub use archetype::{ARCHETYPES_PATH, ActionConfig, AiArchetypes, Archetype, Consideration};
+use archetype::load_archetypes;
+pub use curve::ResponseCurve;
+pub use history::{AgentHistory, Sample, ScoreHistory, Switch};
+pub(crate) use history::{score_history_window, select_clicked_agent, selected_agent_gizmo};
+use history::record_score_history;
+pub use lod::{AiLod, AiLodSettings, AiLodStats, AiTier};
+pub(crate) use lod::think_time;
+use lod::{publish_lod_metrics, schedule_thinking};
+pub use perception::{Contact, Perception};
+pub(crate) use perception::update_perception;
+pub use skill::{AiDifficulty, Difficulty, PilotSkill};
+pub(crate) use skill::apply_difficulty;
+pub use targeting::{AiTarget, TargetingSettings};
+pub(crate) use targeting::{record_damage, select_targets};
+
+use std::time::Instant;
+
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use metrics::{counter, gauge, histogram};
+
+use crate::telemetry::{ENTITY_LABEL, entity_label};
+
+pub struct UtilityAiPlugin;
+
+impl Plugin for UtilityAiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AiRegistry>()
+            .init_resource::<AiArchetypes>()
+            .init_resource::<AiDifficulty>()
+            .init_resource::<AiLodSettings>()
+            .init_resource::<AiLodStats>()
+            .init_resource::<ScoreHistory>()
+            .register_type::<Thinker>()
+            .register_type::<PilotSkill>()
+            .register_type::<AiLod>()
+            .register_type::<AiLodSettings>()
+            .configure_sets(
+                PreUpdate,
+                (AiSet::Sensing, AiSet::Scorers, AiSet::Inputs, AiSet::Pickers).chain(),
+            )
+            .add_systems(PreUpdate, check_thinker_archetypes.before(AiSet::Pickers))
+            .add_systems(PreUpdate, (apply_difficulty, schedule_thinking).before(AiSet::Sensing))
+            .add_systems(PreUpdate, pick_actions.in_set(AiSet::Pickers))
+            .add_systems(PreUpdate, (publish_lod_metrics, record_score_history).after(AiSet::Pickers))
+            .add_systems(Startup, (load_archetypes, validate_archetypes).chain());
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
+pub enum AiSet {
+    /// Agents update what they know about the world, e.g. their targets.
+    Sensing,
+    Scorers,
+    /// Scorer values are copied into each `Thinker`.
+    Inputs,
+    Pickers,
+    Actions,
+}
+
+/// A consideration input. `value` should stay within `0..=1`.
+pub trait Scorer: Component {
+    /// Name archetype data refers to this scorer by.
+    const NAME: &'static str;
+
+    fn value(&self) -> f32;
+}
+
+/// A marker inserted on an agent while it carries the action out.
+pub trait Action: Component + Default {
+    /// Name archetype data refers to this action by.
+    const NAME: &'static str;
+}
+
+struct ActionEntry {
+    name: &'static str,
+    /// Only pilots with it in their `PilotSkill::repertoire` may pick it.
+    skilled: bool,
+    insert: fn(&mut EntityCommands),
+    remove: fn(&mut EntityCommands),
+}
+
+/// Every scorer and action the app knows about.
+#[derive(Resource, Default)]
+pub struct AiRegistry {
+    scorers: Vec<&'static str>,
+    actions: Vec<ActionEntry>,
+}
+
+impl AiRegistry {
+    pub fn scorer_index(&self, name: &str) -> Option<usize> {
+        self.scorers.iter().position(|scorer| *scorer == name)
+    }
+
+    pub fn has_action(&self, name: &str) -> bool {
+        self.action(name).is_some()
+    }
+
+    fn action(&self, name: &str) -> Option<&ActionEntry> {
+        self.actions.iter().find(|action| action.name == name)
+    }
+}
+
+fn insert_action<A: Action>(entity: &mut EntityCommands) {
+    entity.insert(A::default());
+}
+
+fn remove_action<A: Action>(entity: &mut EntityCommands) {
+    entity.remove::<A>();
+}
+
+pub trait UtilityAiAppExt {
+    /// Makes `S` available to archetypes as an input named `S::NAME`. The
+    /// system that computes it is added separately, in `AiSet::Scorers`.
+    fn register_ai_scorer<S: Scorer>(&mut self) -> &mut Self;
+
+    /// Makes `A` available to archetypes as an action named `A::NAME`. The
+    /// systems that carry it out are added separately, in `AiSet::Actions`.
+    fn register_ai_action<A: Action>(&mut self) -> &mut Self;
+
+    /// Like `register_ai_action`, but agents with a `PilotSkill` can only
+    /// pick `A` if it's in their repertoire.
+    fn register_skilled_ai_action<A: Action>(&mut self) -> &mut Self;
+}
+
+impl UtilityAiAppExt for App {
+    fn register_ai_scorer<S: Scorer>(&mut self) -> &mut Self {
+        let mut registry = self.world_mut().get_resource_or_init::<AiRegistry>();
+        if registry.scorer_index(S::NAME).is_some() {
+            warn!("AI scorer {:?} registered twice", S::NAME);
+            return self;
+        }
+        let index = registry.scorers.len();
+        registry.scorers.push(S::NAME);
+        self.add_systems(
+            PreUpdate,
+            (move |mut agents: Query<(&S, &mut Thinker)>| {
+                for (scorer, mut thinker) in &mut agents {
+                    if thinker.inputs.len() <= index {
+                        thinker.inputs.resize(index + 1, 0.0);
+                    }
+                    thinker.inputs[index] = scorer.value();
+                }
+            })
+            .in_set(AiSet::Inputs),
+        )
+    }
+
+    fn register_ai_action<A: Action>(&mut self) -> &mut Self {
+        register_action::<A>(self, false)
+    }
+
+    fn register_skilled_ai_action<A: Action>(&mut self) -> &mut Self {
+        register_action::<A>(self, true)
+    }
+}
+
+fn register_action<A: Action>(app: &mut App, skilled: bool) -> &mut App {
+    let mut registry = app.world_mut().get_resource_or_init::<AiRegistry>();
+    if registry.has_action(A::NAME) {
+        warn!("AI action {:?} registered twice", A::NAME);
+        return app;
+    }
+    registry.actions.push(ActionEntry {
+        name: A::NAME,
+        skilled,
+        insert: insert_action::<A>,
+        remove: remove_action::<A>,
+    });
+    app
+}
+
+/// Set by actions that run to completion. See the module docs.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionStatus {
+    Running,
+    Finished,
+}
+
+/// An agent's utility state.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+#[require(AiLod)]
+pub struct Thinker {
+    /// Key into `AiArchetypes`.
+    pub archetype: String,
+    /// Latest value of each registered scorer.
+    pub inputs: Vec<f32>,
+    /// Utility of each of the archetype's actions at the last pick.
+    pub scores: Vec<f32>,
+    #[reflect(ignore)]
+    pub current_action: Option<&'static str>,
+    /// Index of `current_action` in the archetype's action list.
+    current: Option<usize>,
+    /// Seconds since `current_action` was picked.
+    running_for: f32,
+}
+
+impl Default for Thinker {
+    fn default() -> Self {
+        Self::new("fighter")
+    }
+}
+
+impl Thinker {
+    pub fn new(archetype: impl Into<String>) -> Self {
+        Self {
+            archetype: archetype.into(),
+            inputs: Vec::new(),
+            scores: Vec::new(),
+            current_action: None,
+            current: None,
+            running_for: 0.0,
+        }
+    }
+}
+
+/// Scores every action in each agent's archetype and swaps action markers
+/// when the pick changes.
+#[allow(clippy::type_complexity)]
+fn pick_actions(
+    mut commands: Commands,
+    time: Res<Time>,
+    registry: Res<AiRegistry>,
+    archetypes: Res<AiArchetypes>,
+    mut stats: ResMut<AiLodStats>,
+    mut agents: Query<(Entity, &mut Thinker, &AiLod, Option<&ActionStatus>, Option<&PilotSkill>)>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut thinker, lod, status, skill) in &mut agents {
+        let thinker = &mut *thinker;
+        let Some(archetype) = archetypes.get(&thinker.archetype) else {
+            continue;
+        };
+        thinker.running_for += dt;
+        if !lod.thinking() {
+            continue;
+        }
+        let started = Instant::now();
+        'pick: {
+            match status {
+                Some(ActionStatus::Running) => break 'pick,
+                // Forget the finished action so even re-picking it restarts it.
+                Some(ActionStatus::Finished) => {
+                    commands.entity(entity).remove::<ActionStatus>();
+                    thinker.current = None;
+                    thinker.running_for = 0.0;
+                }
+                None => {}
+            }
+
+            let inputs = &thinker.inputs;
+            let input = |name: &str| {
+                registry
+                    .scorer_index(name)
+                    .and_then(|index| inputs.get(index))
+                    .copied()
+                    .unwrap_or(0.0)
+            };
+            thinker.scores.clear();
+            let can_pick = |action: &str| {
+                skill.is_none_or(|skill| {
+                    registry.action(action).is_none_or(|entry| !entry.skilled) || skill.knows(action)
+                })
+            };
+            thinker.scores.extend(archetype.actions.iter().map(|action| {
+                if can_pick(&action.action) {
+                    action.score(input)
+                } else {
+                    0.0
+                }
+            }));
+            for (action, score) in archetype.actions.iter().zip(&thinker.scores) {
+                gauge!(
+                    "ai.action_score",
+                    ENTITY_LABEL => entity_label(entity),
+                    "action" => action.action.clone(),
+                )
+                .set(*score);
+            }
+
+            let current = thinker.current.filter(|&index| index < archetype.actions.len());
+            let Some(picked) = archetype.choose(&thinker.scores, current, thinker.running_for) else {
+                break 'pick;
+            };
+            histogram!("ai.score_dist").record(thinker.scores[picked]);
+            if Some(picked) == thinker.current {
+                break 'pick;
+            }
+
+            let Some(next) = registry.action(&archetype.actions[picked].action) else {
+                break 'pick;
+            };
+            let mut entity_commands = commands.entity(entity);
+            if let Some(previous) = thinker.current_action.and_then(|name| registry.action(name)) {
+                (previous.remove)(&mut entity_commands);
+            }
+            (next.insert)(&mut entity_commands);
+            counter!("ai.action_switches").increment(1);
+            thinker.current = Some(picked);
+            thinker.current_action = Some(next.name);
+            thinker.running_for = 0.0;
+        }
+        stats.record(lod.tier, started);
+    }
+}
+
+/// Reports new agents whose archetype isn't in `AiArchetypes`, once each;
+/// `pick_actions` skips them.
+fn check_thinker_archetypes(
+    archetypes: Res<AiArchetypes>,
+    thinkers: Query<(Entity, &Thinker), Added<Thinker>>,
+) {
+    for (entity, thinker) in &thinkers {
+        if archetypes.get(&thinker.archetype).is_none() {
+            warn!("AI agent {entity}: unknown archetype {:?}, it will never act", thinker.archetype);
+        }
+    }
+}
+
+/// Reports archetype entries naming scorers or actions nobody registered.
+fn validate_archetypes(registry: Res<AiRegistry>, archetypes: Res<AiArchetypes>) {
+    for (name, archetype) in &archetypes.0 {
+        if !registry.has_action(&archetype.default_action) {
+            warn!("AI archetype {name:?}: unknown default action {:?}", archetype.default_action);
+        }
+        for action in &archetype.actions {
+            if !registry.has_action(&action.action) {
+                warn!("AI archetype {name:?}: unknown action {:?}", action.action);
+            }
+            for consideration in &action.considerations {
+                if registry.scorer_index(&consideration.input).is_none() {
+                    warn!("AI archetype {name:?}: unknown scorer {:?}", consideration.input);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! What each AI agent actually knows about.
+//!
+//! An agent with a `Perception` only targets hostiles it has detected. A
+//! hostile has to be within sensor range, inside the field of view (or
+//! close enough to sense regardless) and in line of sight, checked with a
+//! `SpatialQuery` ray cast. It also has to stay that way for
+//! `detection_delay` before it counts, so a ship flashing across the
+//! canopy isn't instantly tracked. Once lost from view, a detected contact
+//! is remembered for `memory` seconds, and re-detected at once if seen
+//! again in that time.
+//!
+//! Two things bypass the sensors: getting shot reveals the attacker
+//! wherever it is, and squadrons pool their detected contacts (see
+//! `squadron`). Agents without a `Perception` fall back to seeing every
+//! hostile within `TargetingSettings::sensor_range`.
+//!
+//! Sensors only sweep when the agent thinks (see `lod`), so a contact's
+//! exposure and memory are counted in the time between thinks.
+
+use std::time::Instant;
+
+use bevy::prelude::*;
+use metrics::counter;
+
+use avian3d::prelude::{LinearVelocity, SpatialQuery, SpatialQueryFilter};
+
+use crate::combat::{AiMarker, ShipDamaged};
+use crate::faction::{Faction, FactionRelations};
+use crate::spatial::SpatialIndex;
+use crate::subsystems::{SubsystemKind, Subsystems, subsystem_efficiency};
+use crate::weapons::Projectile;
+
+use super::{AiLod, AiLodStats, think_time};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Detected rather than merely glimpsed.
+    pub detected: bool,
+    /// Seen, shared or revealed this frame, so `position` is current.
+    pub fresh: bool,
+    /// Seconds in continuous view, up to the detection delay.
+    exposure: f32,
+    /// Seconds since last fresh.
+    unseen_for: f32,
+}
+
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Perception {
+    pub range: f32,
+    /// Half-angle, in radians, either side of the nose.
+    pub field_of_view: f32,
+    /// Hostiles this close are sensed whichever way the ship is facing.
+    pub proximity: f32,
+    /// Seconds a hostile must stay in view before it's detected.
+    pub detection_delay: f32,
+    /// Seconds a detected contact is remembered once out of view.
+    pub memory: f32,
+    #[reflect(ignore)]
+    contacts: Vec<Contact>,
+}
+
+impl Default for Perception {
+    fn default() -> Self {
+        Self {
+            range: 100.0,
+            field_of_view: 1.2,
+            proximity: 10.0,
+            detection_delay: 0.5,
+            memory: 5.0,
+            contacts: Vec::new(),
+        }
+    }
+}
+
+impl Perception {
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    /// Detected contacts with up-to-date positions.
+    pub fn current(&self) -> impl Iterator<Item = &Contact> {
+        self.contacts.iter().filter(|contact| contact.detected && contact.fresh)
+    }
+
+    /// Whether something at `offset` from the ship is within sensor
+    /// geometry, before line of sight.
+    pub fn covers(&self, forward: Vec3, offset: Vec3, range: f32) -> bool {
+        let distance = offset.length();
+        distance <= self.proximity
+            || (distance <= range && forward.angle_between(offset) <= self.field_of_view)
+    }
+
+    fn contact_mut(&mut self, entity: Entity, position: Vec3, velocity: Vec3) -> &mut Contact {
+        let index = match self.contacts.iter().position(|contact| contact.entity == entity) {
+            Some(index) => index,
+            None => {
+                self.contacts.push(Contact {
+                    entity,
+                    position,
+                    velocity,
+                    detected: false,
+                    fresh: false,
+                    exposure: 0.0,
+                    unseen_for: 0.0,
+                });
+                self.contacts.len() - 1
+            }
+        };
+        let contact = &mut self.contacts[index];
+        contact.position = position;
+        contact.velocity = velocity;
+        contact
+    }
+
+    /// Starts a frame: nothing is fresh until seen again.
+    pub fn begin_frame(&mut self) {
+        for contact in &mut self.contacts {
+            contact.fresh = false;
+        }
+    }
+
+    /// The sensors have `entity` in view this frame.
+    pub fn see(&mut self, entity: Entity, position: Vec3, velocity: Vec3, dt: f32) {
+        let delay = self.detection_delay;
+        let contact = self.contact_mut(entity, position, velocity);
+        contact.exposure = (contact.exposure + dt).min(delay);
+        contact.fresh = true;
+        // Something recently tracked is picked straight back up.
+        contact.detected |= contact.exposure >= delay;
+    }
+
+    /// Learn of `entity` without seeing it: it shot us, or a squadmate
+    /// has it.
+    pub fn reveal(&mut self, entity: Entity, position: Vec3, velocity: Vec3) {
+        let contact = self.contact_mut(entity, position, velocity);
+        if !contact.detected {
+            counter!("ai.contacts_revealed").increment(1);
+        }
+        contact.detected = true;
+        contact.fresh = true;
+        // Squadmates reveal after `end_frame`, which would otherwise never
+        // see this contact as fresh.
+        contact.unseen_for = 0.0;
+    }
+
+    /// Ends a frame: contacts out of view lose exposure, and detected
+    /// ones unseen for longer than `memory` are forgotten.
+    pub fn end_frame(&mut self, dt: f32) {
+        let memory = self.memory;
+        for contact in &mut self.contacts {
+            if contact.fresh {
+                contact.unseen_for = 0.0;
+            } else {
+                contact.unseen_for += dt;
+                if !contact.detected {
+                    contact.exposure = (contact.exposure - dt).max(0.0);
+                }
+            }
+        }
+        self.contacts.retain(|contact| {
+            if contact.detected {
+                contact.unseen_for <= memory
+            } else {
+                contact.exposure > 0.0
+            }
+        });
+    }
+}
+
+/// Runs each agent's sensors, then reveals whoever shot it.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn update_perception(
+    time: Res<Time>,
+    relations: Res<FactionRelations>,
+    index: Res<SpatialIndex>,
+    spatial: SpatialQuery,
+    mut stats: ResMut<AiLodStats>,
+    mut damaged: MessageReader<ShipDamaged>,
+    mut agents: Query<
+        (Entity, &Transform, &Faction, &mut Perception, Option<&Subsystems>, Option<&AiLod>),
+        With<AiMarker>,
+    >,
+    ships: Query<(&Transform, Option<&LinearVelocity>)>,
+    projectiles: Query<(), With<Projectile>>,
+) {
+    let dt = time.delta_secs();
+    for (agent, transform, faction, mut perception, subsystems, lod) in &mut agents {
+        let Some(dt) = think_time(lod, dt) else {
+            continue;
+        };
+        let started = Instant::now();
+        perception.begin_frame();
+        let origin = transform.translation;
+        let forward = *transform.forward();
+        let range = perception.range * subsystem_efficiency(subsystems, SubsystemKind::Sensors);
+        let filter = SpatialQueryFilter::from_excluded_entities([agent]);
+
+        for (entry, distance) in index.within_radius(origin, range.max(perception.proximity)) {
+            if entry.entity == agent || !relations.is_hostile(*faction, entry.faction) {
+                continue;
+            }
+            let offset = entry.position - origin;
+            if !perception.covers(forward, offset, range) {
+                continue;
+            }
+            let Ok(direction) = Dir3::new(offset) else {
+                continue;
+            };
+            // Anything solid in between, bar shots in flight, blocks the view.
+            let blocked = spatial
+                .cast_ray_predicate(origin, direction, distance, true, &filter, &|hit| {
+                    hit != entry.entity && !projectiles.contains(hit)
+                })
+                .is_some();
+            if blocked {
+                continue;
+            }
+            let velocity = ships
+                .get(entry.entity)
+                .ok()
+                .and_then(|(_, velocity)| velocity)
+                .map_or(Vec3::ZERO, |velocity| velocity.0);
+            perception.see(entry.entity, entry.position, velocity, dt);
+        }
+        if let Some(lod) = lod {
+            stats.record(lod.tier, started);
+        }
+    }
+
+    for event in damaged.read() {
+        let Ok((_, _, _, mut perception, _, _)) = agents.get_mut(event.ship) else {
+            continue;
+        };
+        let Ok((attacker, velocity)) = ships.get(event.attacker) else {
+            continue;
+        };
+        perception.reveal(
+            event.attacker,
+            attacker.translation,
+            velocity.map_or(Vec3::ZERO, |velocity| velocity.0),
+        );
+    }
+
+    for (_, _, _, mut perception, _, lod) in &mut agents {
+        if let Some(dt) = think_time(lod, dt) {
+            perception.end_frame(dt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hostile() -> Entity {
+        Entity::from_raw_u32(7).unwrap()
+    }
+
+    #[test]
+    fn detection_needs_sustained_view_unless_revealed() {
+        let mut perception = Perception::default();
+        perception.begin_frame();
+        perception.see(hostile(), Vec3::NEG_Z * 20.0, Vec3::ZERO, 0.2);
+        perception.end_frame(0.2);
+        assert_eq!(perception.current().count(), 0);
+        for _ in 0..3 {
+            perception.begin_frame();
+            perception.see(hostile(), Vec3::NEG_Z * 20.0, Vec3::ZERO, 0.2);
+            perception.end_frame(0.2);
+        }
+        assert_eq!(perception.current().count(), 1);
+
+        let mut shot = Perception::default();
+        shot.begin_frame();
+        shot.reveal(hostile(), Vec3::Z * 80.0, Vec3::ZERO);
+        shot.end_frame(0.016);
+        assert_eq!(shot.current().count(), 1);
+    }
+
+    #[test]
+    fn lost_contacts_are_remembered_then_forgotten() {
+        let mut perception = Perception::default();
+        perception.begin_frame();
+        perception.reveal(hostile(), Vec3::NEG_Z * 20.0, Vec3::ZERO);
+        perception.end_frame(0.1);
+        perception.begin_frame();
+        perception.end_frame(1.0);
+        assert_eq!(perception.contacts().len(), 1);
+        assert_eq!(perception.current().count(), 0);
+        // Back in view: picked straight back up, no delay.
+        perception.begin_frame();
+        perception.see(hostile(), Vec3::NEG_Z * 20.0, Vec3::ZERO, 0.016);
+        perception.end_frame(0.016);
+        assert_eq!(perception.current().count(), 1);
+        perception.begin_frame();
+        perception.end_frame(perception.memory + 1.0);
+        assert!(perception.contacts().is_empty());
+    }
+
+    #[test]
+    fn revealing_after_the_frame_keeps_contacts_alive() {
+        // A squadmate's contact, shared after this ship's own sensing.
+        let mut perception = Perception::default();
+        perception.reveal(hostile(), Vec3::NEG_Z * 150.0, Vec3::ZERO);
+        for _ in 0..(perception.memory as usize + 3) {
+            perception.begin_frame();
+            perception.end_frame(1.0);
+            assert_eq!(perception.contacts().len(), 1);
+            perception.reveal(hostile(), Vec3::NEG_Z * 150.0, Vec3::ZERO);
+        }
+    }
+
+    #[test]
+    fn coverage_is_the_cone_plus_proximity() {
+        let perception = Perception::default();
+        assert!(perception.covers(Vec3::NEG_Z, Vec3::NEG_Z * 50.0, 100.0));
+        assert!(!perception.covers(Vec3::NEG_Z, Vec3::Z * 50.0, 100.0));
+        assert!(perception.covers(Vec3::NEG_Z, Vec3::Z * 5.0, 100.0));
+        assert!(!perception.covers(Vec3::NEG_Z, Vec3::NEG_Z * 150.0, 100.0));
+    }
+}
@@ -0,0 +1,259 @@
+//! How well each AI pilot flies and fights.
+//!
+//! A `PilotSkill` is authored per ship (or left at the regular default)
+//! and scaled once, when it's added, by the global `AiDifficulty`, so the
+//! difficulty is best set before ships spawn — at game start.
+//! Each field feeds a different part of the AI:
+//!
+//! - `reaction_time` is added to the ship's `Perception` detection delay.
+//! - `aim_error` and `lead_accuracy` decide where the ship points when it
+//!   means to shoot (`PilotSkill::aim_point`). Guns fire along the nose,
+//!   and the fire action and maneuvers open fire once the nose is on that
+//!   point rather than on the target, so a poor aim point means misses.
+//! - `repertoire` lists the skilled actions, i.e. dogfight maneuvers, the
+//!   pilot knows; the picker scores the others at zero.
+//! - `aggression` closes the standoff distance and widens the cone the
+//!   pilot opens fire in.
+//! - `courage` damps how threatened the pilot feels, and sets how hurt it
+//!   has to be to fall back and repair when it evades, and how healed
+//!   before it comes back (see `retreat`).
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::weapons::intercept_point;
+
+use super::Perception;
+
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct PilotSkill {
+    /// Seconds between something coming into view and the pilot noticing.
+    pub reaction_time: f32,
+    /// Radius, in radians, of the cone aimed shots wander within.
+    pub aim_error: f32,
+    /// How far towards the true intercept point the pilot leads a moving
+    /// target, from 0 (aims straight at it) to 1.
+    pub lead_accuracy: f32,
+    /// Skilled actions the pilot can pick, by name.
+    pub repertoire: Vec<String>,
+    /// 0..=1; 0.5 is neutral.
+    pub aggression: f32,
+    /// 0..=1; 0.5 is neutral.
+    pub courage: f32,
+}
+
+impl Default for PilotSkill {
+    fn default() -> Self {
+        Self {
+            reaction_time: 0.3,
+            aim_error: 0.08,
+            lead_accuracy: 0.7,
+            repertoire: [
+                "strafing_run",
+                "break_turn",
+                "barrel_roll",
+                "immelmann",
+                "orbit",
+                "joust",
+            ]
+            .map(String::from)
+            .to_vec(),
+            aggression: 0.5,
+            courage: 0.5,
+        }
+    }
+}
+
+impl PilotSkill {
+    pub fn knows(&self, action: &str) -> bool {
+        self.repertoire.iter().any(|known| known == action)
+    }
+
+    /// Multiplier on distances the pilot keeps from its target.
+    pub fn standoff_factor(&self) -> f32 {
+        1.5 - self.aggression
+    }
+
+    /// Multiplier on the cone the pilot opens fire in.
+    pub fn fire_cone_factor(&self) -> f32 {
+        0.5 + self.aggression
+    }
+
+    /// Multiplier on how threatened the pilot feels.
+    pub fn threat_factor(&self) -> f32 {
+        1.5 - self.courage
+    }
+
+    /// Fraction of max health at or below which an evading pilot falls
+    /// back to regroup and repair rather than just breaking off.
+    pub fn retreat_health(&self) -> f32 {
+        0.6 - 0.5 * self.courage
+    }
+
+    /// Fraction of max health a repairing pilot waits for before
+    /// re-engaging.
+    pub fn reengage_health(&self) -> f32 {
+        0.95 - 0.35 * self.courage
+    }
+
+    /// Where the pilot points to shoot a target at `target` moving at
+    /// `velocity` with shots of `shot_speed`. The error drifts smoothly
+    /// with `time` rather than jittering, and `phase` (anything stable per
+    /// ship) keeps pilots from all missing the same way.
+    pub fn aim_point(
+        &self,
+        origin: Vec3,
+        target: Vec3,
+        velocity: Vec3,
+        shot_speed: f32,
+        time: f32,
+        phase: f32,
+    ) -> Vec3 {
+        let lead = intercept_point(origin, target, velocity, shot_speed).unwrap_or(target);
+        let aimed = target.lerp(lead, self.lead_accuracy.clamp(0.0, 1.0));
+        let offset = aimed - origin;
+        let Some(direction) = offset.try_normalize() else {
+            return aimed;
+        };
+        let (a, b) = direction.any_orthonormal_pair();
+        let spin = (time * 0.7 + phase) % TAU;
+        let axis = a * spin.cos() + b * spin.sin();
+        let error = self.aim_error * (0.5 + 0.5 * (time * 1.3 + phase * 2.0).sin());
+        origin + Quat::from_axis_angle(axis, error) * offset
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum Difficulty {
+    Rookie,
+    #[default]
+    Regular,
+    Veteran,
+    Ace,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Rookie,
+        Difficulty::Regular,
+        Difficulty::Veteran,
+        Difficulty::Ace,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Rookie => "rookie",
+            Difficulty::Regular => "regular",
+            Difficulty::Veteran => "veteran",
+            Difficulty::Ace => "ace",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Scales an authored skill to this difficulty.
+    pub fn apply(self, skill: &mut PilotSkill) {
+        // (reaction, aim error, lead, aggression, courage, maneuvers kept)
+        let (reaction, aim, lead, aggression, courage, maneuvers) = match self {
+            Difficulty::Rookie => (2.0, 2.5, 0.3, 0.7, 0.6, Some(2)),
+            Difficulty::Regular => return,
+            Difficulty::Veteran => (0.6, 0.6, 1.3, 1.2, 1.3, None),
+            Difficulty::Ace => (0.3, 0.3, 1.5, 1.4, 1.6, None),
+        };
+        skill.reaction_time *= reaction;
+        skill.aim_error *= aim;
+        skill.lead_accuracy = (skill.lead_accuracy * lead).min(1.0);
+        skill.aggression = (skill.aggression * aggression).min(1.0);
+        skill.courage = (skill.courage * courage).min(1.0);
+        if let Some(maneuvers) = maneuvers {
+            skill.repertoire.truncate(maneuvers);
+        }
+    }
+}
+
+/// The difficulty new AI pilots are scaled to.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AiDifficulty(pub Difficulty);
+
+/// Scales newly added pilot skills to the current difficulty and folds
+/// reaction time into the ship's sensors.
+pub(crate) fn apply_difficulty(
+    difficulty: Res<AiDifficulty>,
+    mut pilots: Query<(&mut PilotSkill, Option<&mut Perception>), Added<PilotSkill>>,
+) {
+    for (mut skill, perception) in &mut pilots {
+        difficulty.0.apply(&mut skill);
+        if let Some(mut perception) = perception {
+            perception.detection_delay += skill.reaction_time;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn harder_pilots_aim_better_and_know_more() {
+        let mut rookie = PilotSkill::default();
+        let mut ace = PilotSkill::default();
+        Difficulty::Rookie.apply(&mut rookie);
+        Difficulty::Ace.apply(&mut ace);
+        assert!(ace.aim_error < rookie.aim_error);
+        assert!(ace.reaction_time < rookie.reaction_time);
+        assert!(ace.lead_accuracy <= 1.0 && ace.lead_accuracy > rookie.lead_accuracy);
+        assert!(ace.knows("joust") && !rookie.knows("joust"));
+        assert_eq!(Difficulty::from_name("Veteran"), Some(Difficulty::Veteran));
+    }
+
+    #[test]
+    fn aim_point_leads_and_stays_within_the_error_cone() {
+        let perfect = PilotSkill {
+            aim_error: 0.0,
+            lead_accuracy: 1.0,
+            ..default()
+        };
+        let crossing = perfect.aim_point(Vec3::ZERO, Vec3::NEG_Z * 50.0, Vec3::X * 20.0, 100.0, 0.0, 0.0);
+        assert!(crossing.x > 0.0);
+
+        let sloppy = PilotSkill {
+            aim_error: 0.2,
+            lead_accuracy: 0.0,
+            ..default()
+        };
+        for step in 0..50 {
+            let aimed = sloppy.aim_point(Vec3::ZERO, Vec3::NEG_Z * 50.0, Vec3::ZERO, 100.0, step as f32 * 0.1, 1.0);
+            assert!(aimed.angle_between(Vec3::NEG_Z) <= 0.2 + 1e-4);
+        }
+    }
+
+    #[test]
+    fn rookies_miss_more_than_aces() {
+        let pilot = |difficulty: Difficulty| {
+            let mut skill = PilotSkill::default();
+            difficulty.apply(&mut skill);
+            skill
+        };
+        // A target crossing 60 ahead, shot at wherever the pilot aims.
+        let (target, velocity, shot_speed) = (Vec3::NEG_Z * 60.0, Vec3::X * 15.0, 100.0);
+        let misses = |skill: &PilotSkill| {
+            (0..200)
+                .filter(|&step| {
+                    let aim = skill.aim_point(Vec3::ZERO, target, velocity, shot_speed, step as f32 * 0.05, 0.0);
+                    // Closest approach between the shot and the target.
+                    let closing = velocity - aim.normalize() * shot_speed;
+                    let t = (-target.dot(closing) / closing.length_squared()).max(0.0);
+                    (target + closing * t).length() > 1.5
+                })
+                .count()
+        };
+        let (rookie, ace) = (misses(&pilot(Difficulty::Rookie)), misses(&pilot(Difficulty::Ace)));
+        assert!(rookie > 2 * ace, "rookie missed {rookie}, ace {ace}");
+    }
+}
@@ -0,0 +1,288 @@
+//! Per-agent target selection and memory.
+//!
+//! `select_targets` is the one place an agent's target is decided; the
+//! scorers, actions and gizmos all read `AiTarget` instead of searching
+//! for the closest hostile themselves. Each hostile the agent knows of —
+//! its `Perception`'s current contacts, or failing that everything in
+//! sensor range — gets a priority from how close it is, how squarely it's
+//! pointed at the agent, and how much damage it has done to the agent
+//! recently. A newly picked
+//! target is held for `retention_time`, and after that is only dropped for
+//! one that beats it by `switch_margin`. A target that slips out of range
+//! is remembered at its last known position for `memory_time`, unless
+//! something else shows up first. A target handed over with `assign`, as a
+//! squadron does, is kept for as long as it keeps being handed over. Like
+//! sensing, this only happens when the agent thinks (see `lod`).
+
+use std::time::Instant;
+
+use bevy::prelude::*;
+use metrics::counter;
+
+use avian3d::prelude::LinearVelocity;
+
+use crate::combat::{AiMarker, Ship, ShipDamaged};
+use crate::faction::{Faction, FactionRelations};
+use crate::spatial::SpatialIndex;
+
+use super::{AiLod, AiLodStats, Perception, think_time};
+
+#[derive(Resource, Debug)]
+pub struct TargetingSettings {
+    /// Hostiles beyond this aren't considered, and a held target beyond it
+    /// is lost.
+    pub sensor_range: f32,
+    /// Seconds a newly picked target is kept no matter what else appears.
+    pub retention_time: f32,
+    /// How much a challenger's priority must exceed the current target's.
+    pub switch_margin: f32,
+    /// Seconds a lost target's last known position is remembered.
+    pub memory_time: f32,
+    pub distance_weight: f32,
+    pub threat_weight: f32,
+    pub damage_weight: f32,
+    /// Exponential decay rate (per second) of remembered damage.
+    pub damage_decay: f32,
+}
+
+impl Default for TargetingSettings {
+    fn default() -> Self {
+        Self {
+            sensor_range: 100.0,
+            retention_time: 2.0,
+            switch_margin: 0.15,
+            memory_time: 5.0,
+            distance_weight: 0.5,
+            threat_weight: 0.3,
+            damage_weight: 0.6,
+            damage_decay: 0.3,
+        }
+    }
+}
+
+impl TargetingSettings {
+    /// Priority of a hostile at `distance` whose nose points at the agent
+    /// by `threat` (`0..=1`) and that recently did `damage` (a fraction of
+    /// the agent's max health).
+    pub fn priority(&self, distance: f32, threat: f32, damage: f32) -> f32 {
+        let closeness = 1.0 - (distance / self.sensor_range).clamp(0.0, 1.0);
+        self.distance_weight * closeness
+            + self.threat_weight * threat.clamp(0.0, 1.0)
+            + self.damage_weight * damage.clamp(0.0, 1.0)
+    }
+
+    /// Whether a challenger with priority `challenger` should replace the
+    /// current target, whose priority is `current` if it's still in
+    /// contact, after it has been held for `held_for` seconds.
+    pub fn should_switch(&self, current: Option<f32>, held_for: f32, challenger: f32) -> bool {
+        match current {
+            None => true,
+            Some(current) => {
+                held_for >= self.retention_time && challenger > current + self.switch_margin
+            }
+        }
+    }
+}
+
+#[derive(Component, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct AiTarget {
+    pub entity: Option<Entity>,
+    /// Where the target is, or was when contact was lost.
+    pub last_known_position: Option<Vec3>,
+    /// How the target is moving, or was when contact was lost.
+    pub last_known_velocity: Vec3,
+    /// Whether the target is currently within sensor range.
+    pub in_contact: bool,
+    /// Seconds since the target was picked.
+    held_for: f32,
+    /// Seconds since contact was lost.
+    lost_for: f32,
+    /// Whether the target was handed over by `assign` since the agent last
+    /// picked for itself. It's kept even if the agent's own sensors can't
+    /// see it, rather than traded for the best of what they can.
+    assigned: bool,
+    /// Recent damage per attacker, as a decaying fraction of max health.
+    #[reflect(ignore)]
+    damage_taken: Vec<(Entity, f32)>,
+}
+
+impl AiTarget {
+    pub fn damage_from(&self, attacker: Entity) -> f32 {
+        self.damage_taken
+            .iter()
+            .find(|(entity, _)| *entity == attacker)
+            .map_or(0.0, |(_, damage)| *damage)
+    }
+
+    /// Takes `entity` as the target on someone else's say-so, e.g. a
+    /// squadron's shared contact.
+    pub(crate) fn assign(&mut self, entity: Entity, position: Vec3, velocity: Vec3, in_contact: bool) {
+        if self.entity != Some(entity) {
+            self.entity = Some(entity);
+            self.held_for = 0.0;
+        }
+        self.last_known_position = Some(position);
+        self.last_known_velocity = velocity;
+        self.in_contact = in_contact;
+        self.lost_for = 0.0;
+        self.assigned = true;
+    }
+
+    fn forget(&mut self) {
+        self.entity = None;
+        self.last_known_position = None;
+        self.last_known_velocity = Vec3::ZERO;
+        self.in_contact = false;
+        self.lost_for = 0.0;
+    }
+}
+
+/// Remembers who has been shooting each agent.
+pub(crate) fn record_damage(
+    mut events: MessageReader<ShipDamaged>,
+    mut agents: Query<(&Ship, &mut AiTarget)>,
+) {
+    for event in events.read() {
+        let Ok((ship, mut target)) = agents.get_mut(event.ship) else {
+            continue;
+        };
+        let damage = event.amount / ship.max_health.max(1.0);
+        match target
+            .damage_taken
+            .iter_mut()
+            .find(|(attacker, _)| *attacker == event.attacker)
+        {
+            Some((_, taken)) => *taken += damage,
+            None => target.damage_taken.push((event.attacker, damage)),
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn select_targets(
+    time: Res<Time>,
+    settings: Res<TargetingSettings>,
+    relations: Res<FactionRelations>,
+    index: Res<SpatialIndex>,
+    mut stats: ResMut<AiLodStats>,
+    mut agents: Query<
+        (Entity, &Transform, &Faction, &mut AiTarget, Option<&Perception>, Option<&AiLod>),
+        With<AiMarker>,
+    >,
+    candidates: Query<(&Transform, Option<&LinearVelocity>), With<Faction>>,
+) {
+    for (agent, transform, faction, mut target, perception, lod) in &mut agents {
+        let Some(dt) = think_time(lod, time.delta_secs()) else {
+            continue;
+        };
+        let started = Instant::now();
+        // Whoever assigned it re-asserts it every frame they still want it.
+        let assigned = std::mem::take(&mut target.assigned);
+        let decay = (-settings.damage_decay * dt).exp();
+        let origin = transform.translation;
+        for (_, damage) in &mut target.damage_taken {
+            *damage *= decay;
+        }
+        target.damage_taken.retain(|(_, damage)| *damage > 0.01);
+
+        let rate = |entity: Entity, position: Vec3, velocity: Vec3| {
+            let threat = candidates.get(entity).map_or(0.0, |(other, _)| {
+                other.forward().dot((origin - position).normalize_or_zero()).max(0.0)
+            });
+            let priority = settings.priority(origin.distance(position), threat, target.damage_from(entity));
+            (entity, position, velocity, priority)
+        };
+        let hostiles: Vec<(Entity, Vec3, Vec3, f32)> = match perception {
+            Some(perception) => perception
+                .current()
+                .map(|contact| rate(contact.entity, contact.position, contact.velocity))
+                .collect(),
+            None => index
+                .within_radius(origin, settings.sensor_range)
+                .filter(|(entry, _)| entry.entity != agent && relations.is_hostile(*faction, entry.faction))
+                .map(|(entry, _)| {
+                    let velocity = candidates
+                        .get(entry.entity)
+                        .ok()
+                        .and_then(|(_, velocity)| velocity)
+                        .map_or(Vec3::ZERO, |velocity| velocity.0);
+                    rate(entry.entity, entry.position, velocity)
+                })
+                .collect(),
+        };
+        let mut current = None;
+        let mut best: Option<(Entity, Vec3, Vec3, f32)> = None;
+        for hostile in hostiles {
+            if Some(hostile.0) == target.entity {
+                current = Some(hostile);
+            }
+            if best.is_none_or(|best| hostile.3 > best.3) {
+                best = Some(hostile);
+            }
+        }
+
+        match (target.entity, current) {
+            (_, Some((_, position, velocity, _))) => {
+                target.last_known_position = Some(position);
+                target.last_known_velocity = velocity;
+                target.in_contact = true;
+                target.lost_for = 0.0;
+            }
+            (Some(entity), None) if candidates.contains(entity) => {
+                target.in_contact = false;
+                target.lost_for += dt;
+                if target.lost_for > settings.memory_time {
+                    target.forget();
+                }
+            }
+            // Destroyed, or never had one.
+            _ => target.forget(),
+        }
+
+        if let Some((entity, position, velocity, priority)) = best
+            && Some(entity) != target.entity
+            && !(assigned && target.entity.is_some())
+            && settings.should_switch(current.map(|current| current.3), target.held_for, priority)
+        {
+            counter!("ai.target_switches").increment(1);
+            target.entity = Some(entity);
+            target.last_known_position = Some(position);
+            target.last_known_velocity = velocity;
+            target.in_contact = true;
+            target.lost_for = 0.0;
+            target.held_for = 0.0;
+        }
+        target.held_for += dt;
+        if let Some(lod) = lod {
+            stats.record(lod.tier, started);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priority_favors_close_threatening_attackers() {
+        let settings = TargetingSettings::default();
+        let far = settings.priority(90.0, 0.0, 0.0);
+        let near = settings.priority(10.0, 0.0, 0.0);
+        let shooting_at_us = settings.priority(90.0, 1.0, 0.5);
+        assert!(near > far);
+        assert!(shooting_at_us > near);
+    }
+
+    #[test]
+    fn retention_and_margin_gate_switching() {
+        let settings = TargetingSettings::default();
+        // Lost the current target: switch straight away.
+        assert!(settings.should_switch(None, 0.0, 0.1));
+        // Much better challenger, but too soon.
+        assert!(!settings.should_switch(Some(0.2), 0.5, 0.9));
+        // Held long enough, but not better by the margin.
+        assert!(!settings.should_switch(Some(0.5), 3.0, 0.6));
+        assert!(settings.should_switch(Some(0.5), 3.0, 0.7));
+    }
+}
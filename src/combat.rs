@@ -1,51 +1,91 @@
+use std::time::Instant;
+
 use bevy::prelude::*;
 use bevy::color::palettes::css::*;
+use bevy::ecs::system::SystemParam;
+use bevy::utils::Parallel;
 
-use avian3d::prelude::LinearVelocity;
+use avian3d::prelude::{AngularVelocity, LinearVelocity, SpatialQuery, SpatialQueryFilter};
 use bevy_egui::*;
 use bevy_egui::egui;
-use metrics::counter;
 use metrics::gauge;
-use metrics::histogram;
 use rand::Rng;
 
-use crate::weapons::HitDetectionSet;
+use crate::ai::{
+    Action, AiArchetypes, AiLod, AiLodStats, AiSet, AiTarget, AiTier, Perception, PilotSkill, Scorer, ScoreHistory,
+    TargetingSettings, Thinker, UtilityAiAppExt, UtilityAiPlugin, record_damage, score_history_window,
+    select_clicked_agent, select_targets, selected_agent_gizmo, update_perception,
+};
+use crate::faction::{Faction, FactionRelations};
+use crate::maneuver::{Maneuver, ManeuverPlugin};
+use crate::retreat::RetreatPlugin;
+use crate::spatial::{SpatialIndex, SpatialPlugin, SpatialSet};
+use crate::squadron::SquadronPlugin;
+use crate::steering::{self, DesiredHeading, Kinematics, SteeringBlend, SteeringLimits, Wander};
+use crate::subsystems::{SubsystemKind, Subsystems, subsystem_efficiency};
+use crate::telemetry::{ENTITY_LABEL, entity_label};
+use crate::weapons::{
+    FireAction, FireInput, HitDetectionSet, Mine, MineContact, Projectile, ProjectileKind,
+    WeaponSettings, fire_weapons, mine_avoidance,
+};
+
+#[derive(Component, Default)]
+pub struct ThreatScore(f32);
+
+impl Scorer for ThreatScore {
+    const NAME: &'static str = "threat";
 
-#[derive(Component, Clone, Copy, PartialEq, Debug, Reflect)]
-#[reflect(Component)]
-pub enum AiAction {
-    Idle,
-    SeekTarget,
-    Evade,
-    Fire
+    fn value(&self) -> f32 {
+        self.0
+    }
 }
 
 
-impl Default for AiAction {
-    fn default() -> Self {
-        AiAction::Idle
+#[derive(Component, Default)]
+pub struct RangeScore(f32);
+
+impl Scorer for RangeScore {
+    const NAME: &'static str = "range";
+
+    fn value(&self) -> f32 {
+        self.0
     }
 }
 
 
+#[derive(Component, Default)]
+pub struct Idle;
 
-#[derive(Component, Default, Reflect)]
-#[reflect(Component)]
-pub struct Thinker {
-    pub scores: Vec<f32>,
-    pub current_action: AiAction,
-    pub threshold: f32,
+impl Action for Idle {
+    const NAME: &'static str = "idle";
 }
 
 #[derive(Component, Default)]
-pub struct ThreatScore(f32);
+pub struct SeekTarget;
 
+impl Action for SeekTarget {
+    const NAME: &'static str = "seek";
+}
 
 #[derive(Component, Default)]
-pub struct RangeScore(f32);
+pub struct Evade;
 
+impl Action for Evade {
+    const NAME: &'static str = "evade";
+}
+
+#[derive(Component, Default)]
+pub struct Fire;
+
+impl Action for Fire {
+    const NAME: &'static str = "fire";
+}
 
+
+/// An AI-flown ship. The action systems steer it through its
+/// `DesiredHeading` rather than writing its rotation.
 #[derive(Component)]
+#[require(DesiredHeading)]
 pub struct AiMarker;
 
 
@@ -57,7 +97,7 @@ pub struct Ship {
 }
 
 /// A brief physical stagger applied on a non-lethal hit: AI control is
-/// suspended (see `action_system`'s `Without<Staggered>` filter) so the
+/// suspended (see the `Without<Staggered>` filter on `ActingAgents`) so the
 /// knockback velocity from `weapons::handle_projectile_hits` can actually
 /// show instead of being overwritten the same frame.
 #[derive(Component)]
@@ -89,6 +129,19 @@ pub struct ShipDestroyed {
     pub position: Vec3,
 }
 
+/// Written for every hit that lands on a `Ship`, whether or not its
+/// shield soaked it up.
+#[derive(Message, Clone, Copy, Debug)]
+pub struct ShipDamaged {
+    pub ship: Entity,
+    /// Owner of the projectile or blast.
+    pub attacker: Entity,
+    /// Damage before shields and resistances.
+    pub amount: f32,
+    /// What actually reached the hull.
+    pub hull_damage: f32,
+}
+
 #[derive(Resource)]
 pub struct CombatSettings {
     pub stagger_duration: f32,
@@ -97,7 +150,32 @@ pub struct CombatSettings {
     pub stagger_linear_damping: f32,
     /// Max rad/s of the random tumble applied on stagger.
     pub stagger_angular_kick: f32,
-    pub evade_speed: f32,
+    /// Evading AI ships push their steering limits up by this factor.
+    pub evade_boost: f32,
+    /// Fraction of its limits an idle AI ship cruises at.
+    pub idle_throttle: f32,
+    /// How close a seeking AI ship settles in behind its target.
+    pub standoff_distance: f32,
+    /// Seeking AI ships start slowing this far out from the standoff.
+    pub slowing_radius: f32,
+    /// Longest look-ahead, in seconds, when leading a moving target.
+    pub max_prediction: f32,
+    /// AI guns go off when the aim point is within this many radians of
+    /// the nose, scaled by the pilot's aggression, and within `fire_range`.
+    /// AI ships only score being in range within that distance too.
+    pub fire_cone: f32,
+    pub fire_range: f32,
+    /// AI ships steer apart from any ship closer than this.
+    pub separation_radius: f32,
+    pub separation_weight: f32,
+    /// Idle AI ships drift towards friendlies within this distance.
+    pub cohesion_radius: f32,
+    pub cohesion_weight: f32,
+    pub wander_weight: f32,
+    pub mine_weight: f32,
+    /// How far ahead along its velocity an AI ship looks for obstacles.
+    pub obstacle_lookahead: f32,
+    pub obstacle_weight: f32,
 }
 
 impl Default for CombatSettings {
@@ -106,23 +184,25 @@ impl Default for CombatSettings {
             stagger_duration: 0.8,
             stagger_linear_damping: 2.0,
             stagger_angular_kick: 3.0,
-            evade_speed: 22.0,
+            evade_boost: 1.25,
+            idle_throttle: 0.4,
+            standoff_distance: 12.0,
+            slowing_radius: 25.0,
+            max_prediction: 2.0,
+            fire_cone: 0.15,
+            fire_range: 50.0,
+            separation_radius: 6.0,
+            separation_weight: 1.5,
+            cohesion_radius: 30.0,
+            cohesion_weight: 0.3,
+            wander_weight: 1.0,
+            mine_weight: 2.0,
+            obstacle_lookahead: 20.0,
+            obstacle_weight: 2.0,
         }
     }
 }
 
-
-#[derive(Component)]
-pub struct AiEnemy;
-
-
-#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
-pub enum AiSet {
-    Scorers,
-    Pickers,
-    Actions
-}
-
 #[derive(Resource, Default, PartialEq)]
 pub struct DebugAiViz(pub bool);
 
@@ -134,217 +214,485 @@ pub struct CombatPlugin;
 
 impl Plugin for CombatPlugin {
     fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<UtilityAiPlugin>() {
+            app.add_plugins(UtilityAiPlugin);
+        }
+        if !app.is_plugin_added::<SpatialPlugin>() {
+            app.add_plugins(SpatialPlugin);
+        }
+        if !app.is_plugin_added::<SquadronPlugin>() {
+            app.add_plugins(SquadronPlugin);
+        }
+        if !app.is_plugin_added::<ManeuverPlugin>() {
+            app.add_plugins(ManeuverPlugin);
+        }
+        if !app.is_plugin_added::<RetreatPlugin>() {
+            app.add_plugins(RetreatPlugin);
+        }
         app
             .init_resource::<DebugAiViz>()
             .init_resource::<AiEnabled>()
             .init_resource::<CombatSettings>()
+            .init_resource::<FactionRelations>()
             .add_message::<ShipDestroyed>()
-            .register_type::<AiAction>()
-            .register_type::<Thinker>()
+            .add_message::<ShipDamaged>()
+            .register_type::<Faction>()
+            .register_ai_scorer::<ThreatScore>()
+            .register_ai_scorer::<RangeScore>()
+            .register_ai_action::<Idle>()
+            .register_ai_action::<SeekTarget>()
+            .register_ai_action::<Evade>()
+            .register_ai_action::<Fire>()
+            .configure_sets(
+                PreUpdate,
+                (AiSet::Sensing, AiSet::Scorers, AiSet::Inputs, AiSet::Pickers)
+                    .run_if(resource_equals(AiEnabled(true))),
+            )
+            .configure_sets(PreUpdate, AiSet::Sensing.after(SpatialSet))
+            .configure_sets(
+                Update,
+                AiSet::Actions
+                    .after(HitDetectionSet)
+                    .run_if(resource_equals(AiEnabled(true))),
+            )
+            .init_resource::<TargetingSettings>()
+            .register_type::<AiTarget>()
+            .register_type::<SteeringLimits>()
+            .register_type::<Wander>()
+            .register_type::<DesiredHeading>()
+            .register_type::<Perception>()
+            .add_systems(
+                PreUpdate,
+                (record_damage, update_perception, select_targets).chain().in_set(AiSet::Sensing),
+            )
             .add_systems(PreUpdate, (
                 threat_scorer_system,
                 range_scorer_system,
-            ).in_set(AiSet::Scorers).run_if(resource_equals(AiEnabled(true))))
-            .add_systems(PreUpdate, picker_system.in_set(AiSet::Pickers).run_if(resource_equals(AiEnabled(true))))
+            ).in_set(AiSet::Scorers))
             .add_systems(
                 Update,
-                action_system
-                    .in_set(AiSet::Actions)
-                    .after(HitDetectionSet)
-                    .run_if(resource_equals(AiEnabled(true))),
+                (seek_action_system, fire_action_system, idle_action_system).in_set(AiSet::Actions),
             )
+            .add_systems(Update, turn_to_heading_system.after(AiSet::Actions))
+            .add_systems(Update, hold_fire.after(AiSet::Actions).before(fire_weapons))
             .add_systems(Update, stagger_decay_system)
             .add_systems(Update, toggle_ai_viz)
             .add_systems(Update, toggle_ai_enabled)
-            .add_systems(EguiPrimaryContextPass, ai_debug_dashboard)
+            .add_systems(EguiPrimaryContextPass, (ai_debug_dashboard, score_history_window))
+            .add_systems(Update, selected_agent_gizmo)
+            .add_observer(select_clicked_agent)
             .add_systems(Update, ai_gizmos_system.run_if(resource_equals(DebugAiViz(true))));
     }
 }
 
 
+/// Closer targets and a more damaged hull feel more threatening; a target
+/// at the edge of sensor range barely registers.
+#[allow(clippy::type_complexity)]
 fn threat_scorer_system(
-    mut query: Query<(&Ship, &Transform, &mut ThreatScore), With<AiMarker>>,
-    enemies: Query<&Transform, With<AiEnemy>>,
+    targeting: Res<TargetingSettings>,
+    mut query: Query<
+        (Entity, &Ship, &Transform, &AiTarget, Option<&PilotSkill>, Option<&AiLod>, &mut ThreatScore),
+        With<AiMarker>,
+    >,
+    mut stats: ResMut<AiLodStats>,
+    mut local_stats: Local<Parallel<AiLodStats>>,
 ) {
 
-    let enemy_positions: Vec<Vec3> = enemies.iter().map(|e| e.translation).collect();
+    query.par_iter_mut().for_each(|(entity, ship, ship_transform, target, skill, lod, mut score)| {
 
-    query.par_iter_mut().for_each(|(ship, ship_transform, mut score)| {
+        if lod.is_some_and(|lod| !lod.thinking()) {
+            return;
+        }
+        let started = Instant::now();
 
-        if enemy_positions.is_empty() {
+        if let Some(target_position) = target.last_known_position {
 
-            info!("No enemies - setting threat score to zero");
-            score.0 = 0.0;
+            let target_dist = ship_transform.translation.distance(target_position);
+            let dist_norm = 1.0 - (target_dist / targeting.sensor_range).clamp(0.0, 1.0);  // 1.0 = close
+            let health_norm = 1.0 - (ship.health / ship.max_health);  // 1.0 = low HP
+            // Braver pilots feel less threatened by the same situation.
+            let nerve = skill.map_or(1.0, PilotSkill::threat_factor);
+            score.0 = (dist_norm * health_norm * 0.6 * nerve).clamp(0.0, 1.0);
 
         } else {
 
-            let closest_dist = enemy_positions
-                .iter()
-                .map(|&pos| ship_transform.translation.distance(pos))
-                .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-                .unwrap_or(f32::MAX);
-
-
-            let dist_norm = 1.0 - (closest_dist / 100.0).clamp(0.0, 1.0);  // 1.0 = close
-            let health_norm = 1.0 - (ship.health / ship.max_health);  // 1.0 = low HP
-            score.0 = (dist_norm * health_norm * 0.6).clamp(0.0, 1.0);
-
-            let entity_id = ship as *const Ship as usize;
-            let threat_gauge = gauge!("ai.threat_score", "entity-id" => format!("{}", entity_id));
-            threat_gauge.set(score.0);
+            score.0 = 0.0;
 
         }
 
-  
+        gauge!("ai.threat_score", ENTITY_LABEL => entity_label(entity)).set(score.0);
+        if let Some(lod) = lod {
+            local_stats.scope(|stats| stats.record(lod.tier, started));
+        }
     });
+    stats.absorb(&mut local_stats);
 
 }
 
 
 // Range Scorer: Similar parallel pattern
+#[allow(clippy::type_complexity)]
 fn range_scorer_system(
-    mut query: Query<(&Ship, &Transform, &mut RangeScore), With<AiMarker>>,
-    enemies: Query<&Transform, With<AiEnemy>>,
+    mut query: Query<(Entity, &Transform, &AiTarget, Option<&AiLod>, &mut RangeScore), With<AiMarker>>,
+    settings: Res<CombatSettings>,
+    mut stats: ResMut<AiLodStats>,
+    mut local_stats: Local<Parallel<AiLodStats>>,
 ) {
-    let enemy_positions: Vec<Vec3> = enemies.iter().map(|e| e.translation).collect();
-    query.par_iter_mut().for_each(|(ship, ship_transform, mut score)| {
-        let in_range = enemy_positions.iter().any(|&pos| ship_transform.translation.distance(pos) <= 50.0);
+    query.par_iter_mut().for_each(|(entity, ship_transform, target, lod, mut score)| {
+        if lod.is_some_and(|lod| !lod.thinking()) {
+            return;
+        }
+        let started = Instant::now();
+        // Only a target we can actually see counts as in range.
+        let in_range = target.in_contact
+            && target
+                .last_known_position
+                .is_some_and(|position| ship_transform.translation.distance(position) <= settings.fire_range);
         score.0 = if in_range {
             0.4
         } else {
             0.0
         };
-        let entity_id = ship as *const Ship as usize;
-        let threat_gauge = gauge!("ai.range_score", "entity-id" => format!("{}", entity_id));
-        threat_gauge.set(score.0);
+        gauge!("ai.range_score", ENTITY_LABEL => entity_label(entity)).set(score.0);
+        if let Some(lod) = lod {
+            local_stats.scope(|stats| stats.record(lod.tier, started));
+        }
     });
+    stats.absorb(&mut local_stats);
 }
 
 
-fn picker_system(
-    mut query: Query<(&ThreatScore, &RangeScore, &mut Thinker), With<AiMarker>>,
-) {
-    for (threat, range, mut thinker) in &mut query.iter_mut() {
-
-        let num_actions = 4;  // AiAction count
-        let mut scores = vec![0.2; num_actions];  // Baseline for Idle
-
-        // Map scores to actions (tune weights/curves here)
-        //
-        // Current issue: threat is 0 when the AI ship is at full health (see health_norm in threat_scorer).
-        // This made Seek score very low (0.12), while Fire got the full range (0.4) > threshold → picker chose Fire (or Idle).
-        // Fire action does nothing, so no movement.
-        //
-        // For basic arcade "chase the player" behavior, give Seek strong weight from range (proximity).
-        // We still keep threat contribution for future "I'm damaged → maybe be more cautious / evade".
-        scores[AiAction::SeekTarget as usize] = range.0 * 0.9 + threat.0 * 0.3;
-        scores[AiAction::Evade as usize] = threat.0 * 0.9;   // lower than before
-        scores[AiAction::Fire as usize] = range.0 * 0.5;     // only consider firing when strongly in range
-
-
-        gauge!("ai.action_score", "action" => "seek").set(scores[AiAction::SeekTarget as usize] as f64);
-        gauge!("ai.action_score", "action" => "evade").set(scores[AiAction::Evade as usize] as f64);
-
-        // Pick highest above threshold
-        let mut best_idx = 0;
-        let mut best_score = thinker.threshold;
-        for (i, &score) in scores.iter().enumerate() {
-            if score > best_score {
-                best_score = score;
-                best_idx = i;
-            }
-        }
-
-        let new_action = unsafe { std::mem::transmute(best_idx as u8) };
-        if new_action != thinker.current_action {
-            counter!("ai.action_switches").increment(1);
-            thinker.current_action = new_action;
-            thinker.scores = scores;  // Cache for debug
-            // Optional: Spawn event for action change
-        }
+pub(crate) type ActingAgents<'w, 's, A> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Faction,
+        &'static AiTarget,
+        &'static Transform,
+        &'static mut LinearVelocity,
+        &'static mut DesiredHeading,
+        Option<&'static Subsystems>,
+        Option<&'static SteeringLimits>,
+        Option<&'static PilotSkill>,
+    ),
+    (With<AiMarker>, With<A>, Without<Staggered>),
+>;
+
+pub(crate) type Mines<'w, 's> = Query<'w, 's, (&'static Transform, &'static Projectile, &'static Mine)>;
+
+pub(crate) fn gather_mines(mines: &Mines) -> Vec<MineContact> {
+    mines
+        .iter()
+        .filter(|(_, _, mine)| mine.is_armed())
+        .map(|(transform, projectile, mine)| (transform.translation, projectile.faction, mine.stealth))
+        .collect()
+}
 
-        histogram!("ai.score_dist").record(best_score as f64);
+/// Everything the AI action systems share for steering.
+#[derive(SystemParam)]
+pub(crate) struct SteeringContext<'w, 's> {
+    pub(crate) time: Res<'w, Time>,
+    pub(crate) settings: Res<'w, CombatSettings>,
+    weapon_settings: Res<'w, WeaponSettings>,
+    relations: Res<'w, FactionRelations>,
+    pub(crate) index: Res<'w, SpatialIndex>,
+    spatial: SpatialQuery<'w, 's>,
+    projectiles: Query<'w, 's, (), With<Projectile>>,
+    lods: Query<'w, 's, &'static AiLod>,
+}
 
+impl SteeringContext<'_, '_> {
+    /// Where `entity` points to shoot at a target at `position` moving at
+    /// `velocity`. Without a `PilotSkill` that's straight at it.
+    pub(crate) fn aim(
+        &self,
+        entity: Entity,
+        skill: Option<&PilotSkill>,
+        origin: Vec3,
+        position: Vec3,
+        velocity: Vec3,
+    ) -> Vec3 {
+        skill.map_or(position, |skill| {
+            skill.aim_point(
+                origin,
+                position,
+                velocity,
+                self.weapon_settings.stats(ProjectileKind::Turret).speed,
+                self.time.elapsed_secs(),
+                entity.index_u32() as f32,
+            )
+        })
     }
-}
 
+    /// Half-angle, in radians, the aim point must be within for the pilot
+    /// to open fire.
+    pub(crate) fn fire_cone(&self, skill: Option<&PilotSkill>) -> f32 {
+        self.settings.fire_cone * skill.map_or(1.0, PilotSkill::fire_cone_factor)
+    }
 
-fn action_system(
-    mut query: Query<(&Thinker, &mut Transform, &mut LinearVelocity), (With<AiMarker>, Without<Staggered>)>,
-    enemies: Query<&Transform, (With<AiEnemy>, Without<AiMarker>)>,
-    settings: Res<CombatSettings>,
-    _time: Res<Time>,
-) {
-    // Collect enemy positions (simple & easy to understand for first pass).
-    // Duplicates logic from scorers; we can extract to a resource later.
-    let enemy_positions: Vec<Vec3> = enemies.iter().map(|t| t.translation).collect();
+    /// The ship's steering limits, cut by damaged engines.
+    pub(crate) fn limits(&self, limits: Option<&SteeringLimits>, subsystems: Option<&Subsystems>) -> SteeringLimits {
+        limits
+            .copied()
+            .unwrap_or_default()
+            .scaled(subsystem_efficiency(subsystems, SubsystemKind::Engines))
+    }
 
-    for (thinker, mut transform, mut linvel) in &mut query {
-        if enemy_positions.is_empty() {
-            continue;
+    /// Terms blended into every AI movement whatever else it's doing:
+    /// keeping clear of other ships, known hostile mines, and anything
+    /// solid dead ahead. `Far` agents go without.
+    pub(crate) fn hazards(
+        &self,
+        entity: Entity,
+        faction: Faction,
+        agent: &Kinematics,
+        mines: &[MineContact],
+        limits: SteeringLimits,
+    ) -> SteeringBlend {
+        if self.lods.get(entity).is_ok_and(|lod| lod.tier == AiTier::Far) {
+            return SteeringBlend::new();
         }
+        let settings = &self.settings;
+        let neighbours = self
+            .index
+            .within_radius(agent.position, settings.separation_radius)
+            .filter(|(entry, _)| entry.entity != entity)
+            .map(|(entry, _)| entry.position);
+        let mines = mine_avoidance(
+            agent.position,
+            faction,
+            &self.relations,
+            mines,
+            self.weapon_settings.mine_detection_range,
+            self.weapon_settings.mine_avoid_radius,
+        );
+        let obstacle = Dir3::new(agent.velocity).ok().and_then(|heading| {
+            self.spatial
+                .cast_ray_predicate(
+                    agent.position,
+                    heading,
+                    settings.obstacle_lookahead,
+                    true,
+                    &SpatialQueryFilter::from_excluded_entities([entity]),
+                    &|hit| !self.projectiles.contains(hit),
+                )
+                .map(|hit| (hit.distance, hit.normal))
+        });
+        SteeringBlend::new()
+            .add(
+                steering::separation(agent, neighbours, settings.separation_radius, limits),
+                settings.separation_weight,
+            )
+            .add(mines * limits.max_acceleration, settings.mine_weight)
+            .add(
+                steering::avoid_obstacle(agent, obstacle, settings.obstacle_lookahead, limits),
+                settings.obstacle_weight,
+            )
+    }
+}
 
-        // Closest threat (same approach as the scorers for conceptual simplicity)
-        let Some(&closest) = enemy_positions.iter().min_by(|a, b| {
-            transform
-                .translation
-                .distance_squared(**a)
-                .partial_cmp(&transform.translation.distance_squared(**b))
-                .unwrap_or(std::cmp::Ordering::Equal)
-        }) else {
+/// Pursues a target at `target_position`, leading it, then eases into
+/// `standoff_distance` rather than ramming it.
+fn close_in(
+    settings: &CombatSettings,
+    agent: &Kinematics,
+    target_position: Vec3,
+    target_velocity: Vec3,
+    skill: Option<&PilotSkill>,
+    limits: SteeringLimits,
+) -> Vec3 {
+    let standoff_distance = settings.standoff_distance * skill.map_or(1.0, PilotSkill::standoff_factor);
+    let offset = target_position - agent.position;
+    if offset.length() > standoff_distance + settings.slowing_radius {
+        steering::pursue(agent, target_position, target_velocity, limits, settings.max_prediction)
+    } else {
+        let standoff = target_position - offset.normalize_or_zero() * standoff_distance;
+        steering::arrive(agent, standoff, settings.slowing_radius, limits)
+    }
+}
+
+/// Pursues the target, leading it, then eases into `standoff_distance`
+/// rather than ramming it.
+fn seek_action_system(
+    mut agents: ParamSet<(ActingAgents<SeekTarget>, Mines)>,
+    steering: SteeringContext,
+) {
+    let mines = gather_mines(&agents.p1());
+    let settings = &steering.settings;
+    let dt = steering.time.delta_secs();
+
+    for (entity, faction, target, transform, mut linvel, mut heading, subsystems, limits, skill) in
+        &mut agents.p0()
+    {
+        // Chases a lost target to where it was last seen.
+        let Some(target_position) = target.last_known_position else {
             continue;
         };
+        let limits = steering.limits(limits, subsystems);
+        let agent = Kinematics {
+            position: transform.translation,
+            velocity: linvel.0,
+        };
 
-        match thinker.current_action {
-            AiAction::SeekTarget => {
-                let dir = (closest - transform.translation).normalize_or_zero();
-                // Face the threat — very satisfying in arcade space combat
-                transform.look_at(closest, Vec3::Y);
+        let chase = close_in(settings, &agent, target_position, target.last_known_velocity, skill, limits);
+        let acceleration = steering
+            .hazards(entity, *faction, &agent, &mines, limits)
+            .add(chase, 1.0)
+            .finish(limits);
+        linvel.0 = steering::integrate(linvel.0, acceleration, dt, limits);
 
-                // Basic constant speed pursuit (easy to reason about)
-                const SEEK_SPEED: f32 = 18.0;
-                **linvel = dir * SEEK_SPEED;
+        // Face the threat — very satisfying in arcade space combat
+        let aim = steering.aim(entity, skill, agent.position, target_position, target.last_known_velocity);
+        heading.0 = Some(aim - agent.position);
+    }
+}
 
-                // TODO (p1-3): arrival / slowing when close so they don't overshoot the player
-            }
-            AiAction::Evade => {
-                // Run: face and fly straight away from the nearest threat.
-                // Its score is zero at full health and only rises as the
-                // ship takes damage (see threat_scorer_system), so this
-                // naturally reads as "fleeing after getting hit".
-                let away = (transform.translation - closest).normalize_or_zero();
-                let evade_point = transform.translation + away * 10.0;
-                transform.look_at(evade_point, Vec3::Y);
-                **linvel = away * settings.evade_speed;
-            }
-            AiAction::Idle | AiAction::Fire => {
-                // TODO: Fire — face target + trigger shooting.
-                // Idle — maybe apply light damping so they don't drift forever.
+/// Nothing to do: cruise about at part throttle, drifting towards nearby
+/// friendlies so idle ships loosely group up.
+fn idle_action_system(
+    mut agents: ParamSet<(ActingAgents<Idle>, Mines)>,
+    mut wanderers: Query<&mut Wander>,
+    steering: SteeringContext,
+) {
+    let mines = gather_mines(&agents.p1());
+    let settings = &steering.settings;
+    let dt = steering.time.delta_secs();
+    let mut rng = rand::thread_rng();
+
+    for (entity, faction, _, transform, mut linvel, mut heading, subsystems, limits, _) in &mut agents.p0() {
+        let limits = steering.limits(limits, subsystems).scaled(settings.idle_throttle);
+        let agent = Kinematics {
+            position: transform.translation,
+            velocity: linvel.0,
+        };
+        let cruise = wanderers.get_mut(entity).map_or(Vec3::ZERO, |mut wander| {
+            steering::wander(&agent, *transform.forward(), &mut wander, dt, &mut rng, limits)
+        });
+        let friends = steering
+            .index
+            .within_radius(agent.position, settings.cohesion_radius)
+            .filter(|(entry, _)| entry.entity != entity && entry.faction == *faction)
+            .map(|(entry, _)| entry.position);
+        let acceleration = steering
+            .hazards(entity, *faction, &agent, &mines, limits)
+            .add(cruise, settings.wander_weight)
+            .add(steering::cohesion(&agent, friends, limits), settings.cohesion_weight)
+            .finish(limits);
+        linvel.0 = steering::integrate(linvel.0, acceleration, dt, limits);
+
+        heading.0 = linvel.0.try_normalize();
+    }
+}
+
+type TurningAgents<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static DesiredHeading,
+        &'static mut AngularVelocity,
+        Option<&'static Subsystems>,
+        Option<&'static SteeringLimits>,
+    ),
+    (With<AiMarker>, Without<Staggered>),
+>;
+
+/// Swings each AI ship's nose towards its `DesiredHeading` through its
+/// angular velocity, within its turn rate. Runs regardless of `AiEnabled`,
+/// so pausing the AI lets ships finish the turn they're in rather than
+/// spin forever.
+fn turn_to_heading_system(mut agents: TurningAgents) {
+    for (transform, heading, mut angvel, subsystems, limits) in &mut agents {
+        let limits = limits
+            .copied()
+            .unwrap_or_default()
+            .scaled(subsystem_efficiency(subsystems, SubsystemKind::Engines));
+        angvel.0 = heading
+            .0
+            .map_or(Vec3::ZERO, |heading| steering::turn_towards(transform.rotation, heading, limits));
+    }
+}
+
+/// Closes in like `seek`, nose on the aim point, and holds the primary
+/// trigger while the target is in sight and the aim point sits inside the
+/// pilot's fire cone and range.
+fn fire_action_system(
+    mut agents: ParamSet<(ActingAgents<Fire>, Mines)>,
+    mut inputs: Query<&mut FireInput>,
+    steering: SteeringContext,
+) {
+    let mines = gather_mines(&agents.p1());
+    let settings = &steering.settings;
+    let dt = steering.time.delta_secs();
+
+    for (entity, faction, target, transform, mut linvel, mut heading, subsystems, limits, skill) in
+        &mut agents.p0()
+    {
+        let mut input = inputs.get_mut(entity).ok();
+        let Some(target_position) = target.last_known_position else {
+            if let Some(input) = input.as_mut() {
+                input.set(FireAction::Primary, false);
             }
+            continue;
+        };
+        let limits = steering.limits(limits, subsystems);
+        let agent = Kinematics {
+            position: transform.translation,
+            velocity: linvel.0,
+        };
+
+        let chase = close_in(settings, &agent, target_position, target.last_known_velocity, skill, limits);
+        let acceleration = steering
+            .hazards(entity, *faction, &agent, &mines, limits)
+            .add(chase, 1.0)
+            .finish(limits);
+        linvel.0 = steering::integrate(linvel.0, acceleration, dt, limits);
+
+        let aim = steering.aim(entity, skill, agent.position, target_position, target.last_known_velocity)
+            - agent.position;
+        heading.0 = Some(aim);
+        if let Some(input) = input.as_mut() {
+            let firing = target.in_contact
+                && aim.length() <= settings.fire_range
+                && transform.forward().angle_between(aim) <= steering.fire_cone(skill);
+            input.set(FireAction::Primary, firing);
+        }
+    }
+}
+
+/// Lets go of the guns of AI ships nothing fired this frame: neither
+/// firing nor maneuvering, staggered, or with `AiEnabled` off. Whatever
+/// does fire sets `FireInput` itself in `AiSet::Actions`.
+#[allow(clippy::type_complexity)]
+fn hold_fire(
+    enabled: Res<AiEnabled>,
+    mut agents: Query<(&mut FireInput, Has<Fire>, Has<Maneuver>, Has<Staggered>), With<AiMarker>>,
+) {
+    for (mut input, firing, maneuvering, staggered) in &mut agents {
+        if !enabled.0 || !(firing || maneuvering) || staggered {
+            input.set(FireAction::Primary, false);
         }
     }
 }
 
 /// Decays a staggered ship's leftover velocity/tumble and hands control
-/// back to `action_system` once the stagger window ends. Runs regardless
+/// back to the AI action systems once the stagger window ends. Runs regardless
 /// of `AiEnabled` — a hit reaction shouldn't depend on the AI debug toggle.
 fn stagger_decay_system(
     mut commands: Commands,
     time: Res<Time>,
     settings: Res<CombatSettings>,
-    mut query: Query<(Entity, &mut Staggered, &mut Transform, &mut LinearVelocity)>,
+    mut query: Query<(Entity, &mut Staggered, &mut LinearVelocity, &mut AngularVelocity)>,
 ) {
     let dt = time.delta_secs();
-    for (entity, mut staggered, mut transform, mut linvel) in &mut query {
+    for (entity, mut staggered, mut linvel, mut angvel) in &mut query {
         let decay = (-settings.stagger_linear_damping * dt).exp();
         **linvel *= decay;
 
-        let angular_velocity = staggered.angular_velocity * decay;
-        staggered.angular_velocity = angular_velocity;
-        if angular_velocity.length_squared() > 1e-6 {
-            transform.rotation =
-                (transform.rotation * Quat::from_scaled_axis(angular_velocity * dt)).normalize();
-        }
+        staggered.angular_velocity *= decay;
+        angvel.0 = staggered.angular_velocity;
 
         if staggered.timer.tick(time.delta()).is_finished() {
             commands.entity(entity).remove::<Staggered>();
@@ -368,27 +716,25 @@ fn toggle_ai_enabled(mut enabled: ResMut<AiEnabled>, keys: Res<ButtonInput<KeyCo
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn ai_gizmos_system(
     mut gizmos: Gizmos,
-    ai_query: Query<(&Transform, &Ship, &Thinker, &ThreatScore, &RangeScore), With<AiMarker>>,
-    enemies: Query<&Transform, With<AiEnemy>>,
+    ai_query: Query<(&Transform, &Thinker, &AiTarget, &ThreatScore, &RangeScore), With<AiMarker>>,
     viz: Res<DebugAiViz>,
 ) {
 
     if !viz.0 { return; }
 
-    let enemy_positions: Vec<Vec3> = enemies.iter().map(|e| e.translation).collect();
-
-    for (ship_transform, _, thinker, threat, range) in &ai_query {
+    for (ship_transform, thinker, target, threat, range) in &ai_query {
 
         let pos = ship_transform.translation;
         let radius = 3.0 + range.0 + 15.0;
 
         let ring_color = match thinker.current_action {
-            AiAction::Idle => Color::from(GRAY),
-            AiAction::SeekTarget => Color::from(TURQUOISE),
-            AiAction::Evade => Color::from(RED),
-            AiAction::Fire => Color::from(YELLOW),
+            Some(SeekTarget::NAME) => Color::from(TURQUOISE),
+            Some(Evade::NAME) => Color::from(RED),
+            Some(Fire::NAME) => Color::from(YELLOW),
+            _ => Color::from(GRAY),
         };
 
         gizmos.circle(pos, radius, ring_color);
@@ -405,43 +751,46 @@ fn ai_gizmos_system(
             threat_color,
         );
 
-        // Seek/Fire: Line to closest enemy
-        if matches!(thinker.current_action, AiAction::SeekTarget | AiAction::Fire) && !enemy_positions.is_empty() {
-            if let Some(&closest) = enemy_positions.iter().min_by(|a, b| {
-                pos.distance_squared(**a).partial_cmp(&pos.distance_squared(**b)).unwrap()
-            }) {
-                // gizmos.line(pos, closest, Color::WHITE);
-                gizmos.arrow(pos, closest, Color::from(WHITE));
-            }
+        // Seek/Fire: Line to the target, dimmed while it's only remembered
+        if matches!(thinker.current_action, Some(SeekTarget::NAME | Fire::NAME))
+            && let Some(target_position) = target.last_known_position
+        {
+            // gizmos.line(pos, closest, Color::WHITE);
+            let color = if target.in_contact {
+                Color::from(WHITE)
+            } else {
+                Color::from(GRAY).with_alpha(0.5)
+            };
+            gizmos.arrow(pos, target_position, color);
         }
 
 
         let label_pos = pos + Vec3::Y * (radius + 2.0);
         match thinker.current_action {
-            AiAction::Idle => {
-                // Gray dot for idle
-                gizmos.circle(
-                    Isometry3d::new(
-                        label_pos,
-                        Quat::from_rotation_arc(Vec3::Z, Vec3::Y),
-                    ),
-                    0.5,
-                    Color::from(GRAY),
-                );
-            }
-            AiAction::SeekTarget => {
+            Some(SeekTarget::NAME) => {
                 // Cyan arrow pointing forward
                 gizmos.arrow_2d(label_pos.xy(), label_pos.xy() + Vec2::X * 2.0, Color::from(TURQUOISE));
             }
-            AiAction::Evade => {
+            Some(Evade::NAME) => {
                 // Red zigzag line for evasion
                 let zig = [label_pos + Vec3::X * -1.0 + Vec3::Y * 0.5, label_pos + Vec3::X * 1.0 - Vec3::Y * 0.5];
                 gizmos.linestrip_2d([zig[0].xy(), label_pos.xy(), zig[1].xy()], Color::from(RED));
             }
-            AiAction::Fire => {
+            Some(Fire::NAME) => {
                 // Yellow burst (star-like cross)
                 gizmos.cross_2d(label_pos.xy(), 1.0, Color::from(YELLOW));
             }
+            _ => {
+                // Gray dot for idle
+                gizmos.circle(
+                    Isometry3d::new(
+                        label_pos,
+                        Quat::from_rotation_arc(Vec3::Z, Vec3::Y),
+                    ),
+                    0.5,
+                    Color::from(GRAY),
+                );
+            }
         }
 
     }
@@ -451,8 +800,11 @@ fn ai_gizmos_system(
 
 fn ai_debug_dashboard(
     mut contexts: EguiContexts,
-    ai_query: Query<(Entity, &Thinker, &ThreatScore, &RangeScore), With<AiMarker>>,
+    ai_query: Query<(Entity, &Thinker, &AiLod, &ThreatScore, &RangeScore), With<AiMarker>>,
+    archetypes: Res<AiArchetypes>,
     enabled: Res<AiEnabled>,
+    lod_stats: Res<AiLodStats>,
+    mut history: ResMut<ScoreHistory>,
 ) {
     let ctx = match contexts.ctx_mut() {
         Ok(c) => c,
@@ -462,48 +814,73 @@ fn ai_debug_dashboard(
         ui.label(format!("AI Enabled: {}  (press G to toggle)", enabled.0));
         let agents = ai_query.iter().collect::<Vec<_>>();
         ui.label(format!("{} AI Agents Active", agents.len()));
-
-        let action_names = ["Idle", "Seek", "Evade", "Fire"];
+        for tier in AiTier::ALL {
+            let index = tier as usize;
+            ui.label(format!(
+                "{}: {} agents, {} thinking, {:.0} µs",
+                tier.name(),
+                lod_stats.agents[index],
+                lod_stats.thinking[index],
+                lod_stats.think_time[index].as_secs_f64() * 1e6,
+            ));
+        }
+        ui.label(format!("Deferred by budget: {}", lod_stats.deferred));
+        ui.label("Click an agent (or its ship) for its score history.");
 
         egui::ScrollArea::vertical().show(ui, |ui| {
-            for (entity, thinker, threat, range) in agents {
+            for (entity, thinker, lod, threat, range) in agents {
+                let Some(archetype) = archetypes.get(&thinker.archetype) else {
+                    continue;
+                };
                 ui.group(|ui| {
-                    ui.horizontal(|ui| {
-                        ui.strong(format!("Entity #{}: {:?}", entity.index(), thinker.current_action));
-                        // if ui.button("📋 Inspect").clicked() {
-                        //     // Optional: Integrate inspector focus (advanced)
-                        // }
-                    });
+                    let label = format!(
+                        "Entity #{} ({}, {}): {}",
+                        entity.index(),
+                        thinker.archetype,
+                        lod.tier.name(),
+                        thinker.current_action.unwrap_or("-"),
+                    );
+                    if ui
+                        .selectable_label(history.selected == Some(entity), egui::RichText::new(label).strong())
+                        .clicked()
+                    {
+                        history.select(entity);
+                    }
 
                     // Threat/Range rows
                     ui.horizontal(|ui| {
                         ui.label("Threat:");
-                        // ui.add(egui::ProgressBar::new(threat.0).fill(Color32::from_rgba_unmultiplied(255, 77, 77, 255)));
-                        ui.label(format!("{:.2}", threat.0));
+                        ui.add(
+                            egui::ProgressBar::new(threat.0)
+                                .fill(egui::Color32::from_rgb(255, 77, 77))
+                                .text(format!("{:.2}", threat.0)),
+                        );
                     });
-                    
+
                     ui.horizontal(|ui| {
                         ui.label("Range:");
-                        // ui.add(egui::ProgressBar::new(range.0).fill(Color32::from_rgba_unmultiplied(77, 255, 77, 255)));
-                        ui.label(format!("{:.2}", range.0));
+                        ui.add(
+                            egui::ProgressBar::new(range.0)
+                                .fill(egui::Color32::from_rgb(77, 255, 77))
+                                .text(format!("{:.2}", range.0)),
+                        );
                     });
 
                     ui.separator();
 
                     // All action scores as bars
-                    // ui.horizontal_wrapped(|ui| {
-                        for (i, score) in thinker.scores.iter().enumerate() {
-                            let clamped = score.clamp(0.0, 1.0);
-                            ui.horizontal(|ui| {
-                                ui.label(action_names[i]);
-                                // let bar = egui::ProgressBar::new(clamped);
-                                // ui.add(bar.fill(get_action_color(i as u8)));  // Define below
-                                ui.label(format!("{:.2}", clamped));
-                            });
-                        }
-                    // });
-
-                    ui.label(format!("Threshold: {:.2}", thinker.threshold));
+                    for (action, score) in archetype.actions.iter().zip(&thinker.scores) {
+                        let clamped = score.clamp(0.0, 1.0);
+                        ui.horizontal(|ui| {
+                            ui.label(&action.action);
+                            ui.add(egui::ProgressBar::new(clamped).text(format!("{:.2}", clamped)));
+                        });
+                    }
+
+                    ui.label(format!(
+                        "Threshold: {:.2}  Hysteresis: {:.2}",
+                        archetype.threshold, archetype.hysteresis
+                    ));
                     ui.separator();
                 });
             }
@@ -511,8 +888,24 @@ fn ai_debug_dashboard(
     });
 }
 
+/// A world with everything `SteeringContext` needs, for tests of the AI
+/// action systems.
+#[cfg(test)]
+pub(crate) fn test_world() -> World {
+    let mut world = crate::weapons::test_world();
+    world.init_resource::<CombatSettings>();
+    world.init_resource::<FactionRelations>();
+    world.init_resource::<SpatialIndex>();
+    world.init_resource::<avian3d::collider_tree::ColliderTrees>();
+    world
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+
     use super::*;
 
     #[test]
@@ -546,14 +939,44 @@ mod tests {
         let heavy_kick = impulse / 10.0;
         assert!(light_kick.length() > heavy_kick.length());
     }
-}
-
-
-
-
-
-
-
-
 
+    #[test]
+    fn fire_action_shoots_only_with_the_target_in_range_and_on_the_nose() {
+        let mut world = test_world();
+        world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(0.1));
+        let target = world.spawn((Transform::default(), Faction::Player)).id();
+        let mut spawn_agent = |position: Vec3| {
+            let mut ai_target = AiTarget::default();
+            ai_target.entity = Some(target);
+            ai_target.last_known_position = Some(Vec3::ZERO);
+            ai_target.in_contact = true;
+            world
+                .spawn((
+                    AiMarker,
+                    Faction::Raiders,
+                    ai_target,
+                    Transform::from_translation(position),
+                    LinearVelocity::default(),
+                    FireInput::default(),
+                    Fire,
+                ))
+                .id()
+        };
+        let near = spawn_agent(Vec3::new(0.0, 0.0, 30.0));
+        let far = spawn_agent(Vec3::new(0.0, 0.0, 80.0));
+        let abeam = spawn_agent(Vec3::new(30.0, 0.0, 0.0));
 
+        world.run_system_once(fire_action_system).unwrap();
+        let pressed = |world: &World, agent| {
+            world.get::<FireInput>(agent).unwrap().trigger(FireAction::Primary).pressed
+        };
+        assert!(pressed(&world, near));
+        // Out of range: closing in, nose on the target, guns quiet.
+        assert!(!pressed(&world, far));
+        assert!(world.get::<LinearVelocity>(far).unwrap().z < 0.0);
+        // Turning towards it, but not lined up yet.
+        assert!(!pressed(&world, abeam));
+        let heading = world.get::<DesiredHeading>(abeam).unwrap().0.unwrap();
+        assert!(heading.normalize().dot(Vec3::NEG_X) > 0.99);
+    }
+}
@@ -17,6 +17,7 @@ use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, CursorOptions};
 
 use crate::common::{MainCamera, Player};
+use crate::subsystems::{SubsystemKind, Subsystems, subsystem_efficiency};
 use avian3d::prelude::{AngularVelocity, LinearVelocity, PhysicsSystems, Rotation};
 
 pub struct ControllerPlugin;
@@ -115,9 +116,17 @@ fn apply_ship_motion(
     settings: Res<ControllerSettings>,
     keys: Res<ButtonInput<KeyCode>>,
     mut accum: ResMut<MouseAccum>,
-    mut query: Query<(&mut LinearVelocity, &mut AngularVelocity, &mut Rotation), With<Player>>,
+    mut query: Query<
+        (
+            &mut LinearVelocity,
+            &mut AngularVelocity,
+            &mut Rotation,
+            Option<&Subsystems>,
+        ),
+        With<Player>,
+    >,
 ) {
-    let Ok((mut linvel, mut angvel, mut rotation)) = query.single_mut() else {
+    let Ok((mut linvel, mut angvel, mut rotation, subsystems)) = query.single_mut() else {
         return;
     };
     let dt = time.delta_secs();
@@ -167,7 +176,9 @@ fn apply_ship_motion(
     }
 
     let boost = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
-    let max_speed = settings.max_linear_speed * if boost { settings.boost_multiplier } else { 1.0 };
+    let max_speed = settings.max_linear_speed
+        * if boost { settings.boost_multiplier } else { 1.0 }
+        * subsystem_efficiency(subsystems, SubsystemKind::Engines);
 
     if local_dir.length_squared() > 0.0 {
         let world_dir = rotation.0 * local_dir.normalize();
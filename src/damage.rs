@@ -0,0 +1,126 @@
+//! Typed damage and per-ship resistance profiles.
+//!
+//! Every projectile carries a `Damage` (amount plus `DamageType`), and
+//! every ship may carry `Resistances` saying how much of each type its
+//! shield and hull actually take. Balancing lives entirely in the profiles
+//! — energy strong against shields, explosives strong against armor — so
+//! hit resolution never special-cases a weapon.
+
+use bevy::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Reflect)]
+pub enum DamageType {
+    Kinetic,
+    Energy,
+    Explosive,
+    /// Strips shields but barely scratches the hull.
+    Ion,
+}
+
+impl DamageType {
+    pub const ALL: [DamageType; 4] = [
+        DamageType::Kinetic,
+        DamageType::Energy,
+        DamageType::Explosive,
+        DamageType::Ion,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, Reflect)]
+pub struct Damage {
+    pub amount: f32,
+    pub kind: DamageType,
+}
+
+impl Damage {
+    pub const fn new(amount: f32, kind: DamageType) -> Self {
+        Self { amount, kind }
+    }
+
+    pub fn scaled(self, factor: f32) -> Self {
+        Self {
+            amount: self.amount * factor,
+            ..self
+        }
+    }
+}
+
+/// Fraction of each damage type that a ship's shield and hull take,
+/// indexed by `DamageType as usize`. 1.0 is unmodified, below resists,
+/// above is a weakness. Ships without the component use `Default`, the
+/// baseline every archetype is tuned against.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Resistances {
+    pub shield: [f32; 4],
+    pub hull: [f32; 4],
+}
+
+impl Default for Resistances {
+    fn default() -> Self {
+        //                 kinetic energy explosive ion
+        Self {
+            shield: [0.8, 1.5, 0.6, 2.0],
+            hull: [1.0, 0.7, 1.4, 0.1],
+        }
+    }
+}
+
+impl Resistances {
+    /// Thin hull, no armor to speak of: everything bites a little harder.
+    pub fn light_fighter() -> Self {
+        Self {
+            shield: [0.9, 1.6, 0.7, 2.2],
+            hull: [1.2, 0.9, 1.6, 0.15],
+        }
+    }
+
+    /// Plated hull that shrugs off small arms; explosives still crack it.
+    pub fn armored() -> Self {
+        Self {
+            shield: [0.8, 1.5, 0.6, 2.0],
+            hull: [0.5, 0.6, 1.2, 0.05],
+        }
+    }
+
+    pub fn shield_multiplier(&self, kind: DamageType) -> f32 {
+        self.shield[kind as usize]
+    }
+
+    pub fn hull_multiplier(&self, kind: DamageType) -> f32 {
+        self.hull[kind as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baseline_favors_energy_on_shields_and_explosives_on_hull() {
+        let baseline = Resistances::default();
+        let strongest = |table: &dyn Fn(DamageType) -> f32, exclude: DamageType| {
+            DamageType::ALL
+                .into_iter()
+                .filter(|kind| *kind != exclude)
+                .max_by(|a, b| table(*a).total_cmp(&table(*b)))
+                .unwrap()
+        };
+        // Ion is a shield-only specialty; among general weapons energy wins.
+        assert_eq!(
+            strongest(&|kind| baseline.shield_multiplier(kind), DamageType::Ion),
+            DamageType::Energy
+        );
+        assert_eq!(
+            strongest(&|kind| baseline.hull_multiplier(kind), DamageType::Ion),
+            DamageType::Explosive
+        );
+    }
+
+    #[test]
+    fn scaled_damage_keeps_type() {
+        let damage = Damage::new(40.0, DamageType::Explosive).scaled(0.5);
+        assert_eq!(damage.amount, 20.0);
+        assert_eq!(damage.kind, DamageType::Explosive);
+    }
+}
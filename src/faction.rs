@@ -0,0 +1,105 @@
+//! Who fights whom.
+//!
+//! Every ship (player included) carries a `Faction`; `FactionRelations`
+//! says how any two factions regard each other. Anything that needs to
+//! decide "is this a valid target" — AI target selection, missile lock,
+//! projectile damage — asks the relations table instead of checking marker
+//! components, so allied wingmen and AI-vs-AI fights need no special cases.
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub enum Faction {
+    /// Regarded as neutral by, and neutral towards, everyone.
+    #[default]
+    Neutral,
+    Player,
+    Raiders,
+    Syndicate,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stance {
+    Hostile,
+    Neutral,
+    Allied,
+}
+
+#[derive(Resource)]
+pub struct FactionRelations {
+    /// Stances between distinct factions, stored once per unordered pair.
+    /// A faction is always allied with itself; unlisted pairs are neutral.
+    stances: HashMap<(Faction, Faction), Stance>,
+    /// Whether projectiles and blasts damage ships allied with their owner.
+    pub friendly_fire: bool,
+}
+
+impl Default for FactionRelations {
+    fn default() -> Self {
+        let mut relations = Self {
+            stances: HashMap::new(),
+            friendly_fire: false,
+        };
+        relations.set(Faction::Player, Faction::Raiders, Stance::Hostile);
+        relations.set(Faction::Player, Faction::Syndicate, Stance::Hostile);
+        relations.set(Faction::Raiders, Faction::Syndicate, Stance::Hostile);
+        relations
+    }
+}
+
+impl FactionRelations {
+    fn key(a: Faction, b: Faction) -> (Faction, Faction) {
+        if (a as u8) <= (b as u8) { (a, b) } else { (b, a) }
+    }
+
+    pub fn set(&mut self, a: Faction, b: Faction, stance: Stance) {
+        if a != b {
+            self.stances.insert(Self::key(a, b), stance);
+        }
+    }
+
+    pub fn stance(&self, a: Faction, b: Faction) -> Stance {
+        if a == b {
+            return Stance::Allied;
+        }
+        self.stances
+            .get(&Self::key(a, b))
+            .copied()
+            .unwrap_or(Stance::Neutral)
+    }
+
+    pub fn is_hostile(&self, a: Faction, b: Faction) -> bool {
+        self.stance(a, b) == Stance::Hostile
+    }
+
+    /// Whether a hit from `attacker` should damage `victim`: always for
+    /// hostile and neutral targets, for allies only with friendly fire on.
+    pub fn can_damage(&self, attacker: Faction, victim: Faction) -> bool {
+        self.friendly_fire || self.stance(attacker, victim) != Stance::Allied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stances_are_symmetric_and_self_allied() {
+        let relations = FactionRelations::default();
+        assert!(relations.is_hostile(Faction::Raiders, Faction::Player));
+        assert!(relations.is_hostile(Faction::Player, Faction::Raiders));
+        assert_eq!(relations.stance(Faction::Raiders, Faction::Raiders), Stance::Allied);
+        assert_eq!(relations.stance(Faction::Neutral, Faction::Player), Stance::Neutral);
+    }
+
+    #[test]
+    fn friendly_fire_toggle_gates_allied_damage() {
+        let mut relations = FactionRelations::default();
+        assert!(!relations.can_damage(Faction::Player, Faction::Player));
+        assert!(relations.can_damage(Faction::Player, Faction::Neutral));
+        relations.friendly_fire = true;
+        assert!(relations.can_damage(Faction::Player, Faction::Player));
+    }
+}
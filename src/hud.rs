@@ -0,0 +1,150 @@
+//! Text HUD for the player's weapon state: missile lock acquisition
+//! progress, an inbound-missile warning, and the subsystem being aimed at.
+//!
+//! Purely a reader — it owns no gameplay state, only mirrors the player's
+//! `weapons::MissileLock`, `weapons::MissileWarning` and
+//! `subsystems::SubsystemTarget` into text nodes.
+
+use bevy::prelude::*;
+
+use crate::common::Player;
+use crate::subsystems::SubsystemTarget;
+use crate::weapons::{MissileLock, MissileWarning, WeaponSettings};
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_hud)
+            .add_systems(
+                Update,
+                (
+                    update_lock_indicator,
+                    update_missile_warning,
+                    update_subsystem_indicator,
+                ),
+            );
+    }
+}
+
+const HUD_FONT_SIZE: FontSize = FontSize::Px(18.0);
+const LOCKING_COLOR: Color = Color::srgb(1.0, 0.85, 0.2);
+const LOCKED_COLOR: Color = Color::srgb(1.0, 0.2, 0.2);
+
+#[derive(Component)]
+struct LockIndicator;
+
+#[derive(Component)]
+struct WarningIndicator;
+
+#[derive(Component)]
+struct SubsystemIndicator;
+
+fn spawn_hud(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Lock Indicator"),
+        LockIndicator,
+        Text::new(""),
+        TextFont {
+            font_size: HUD_FONT_SIZE,
+            ..default()
+        },
+        TextColor(LOCKING_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Percent(30.0),
+            left: Val::Percent(46.0),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        Name::new("Missile Warning"),
+        WarningIndicator,
+        Text::new(""),
+        TextFont {
+            font_size: HUD_FONT_SIZE,
+            ..default()
+        },
+        TextColor(LOCKED_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(12.0),
+            left: Val::Percent(44.0),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        Name::new("Subsystem Target"),
+        SubsystemIndicator,
+        Text::new(""),
+        TextFont {
+            font_size: HUD_FONT_SIZE,
+            ..default()
+        },
+        TextColor(LOCKING_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Percent(26.0),
+            left: Val::Percent(46.0),
+            ..default()
+        },
+    ));
+}
+
+fn update_lock_indicator(
+    settings: Res<WeaponSettings>,
+    player: Query<&MissileLock, With<Player>>,
+    mut indicator: Query<(&mut Text, &mut TextColor), With<LockIndicator>>,
+) {
+    let Ok((mut text, mut color)) = indicator.single_mut() else {
+        return;
+    };
+    let Ok(lock) = player.single() else {
+        text.0.clear();
+        return;
+    };
+
+    if lock.is_locked(settings.missile_lock_time) {
+        text.0 = "LOCKED".to_string();
+        color.0 = LOCKED_COLOR;
+    } else if lock.target.is_some() {
+        let percent = lock.fraction(settings.missile_lock_time) * 100.0;
+        text.0 = format!("LOCKING {percent:.0}%");
+        color.0 = LOCKING_COLOR;
+    } else {
+        text.0.clear();
+    }
+}
+
+fn update_missile_warning(
+    player: Query<Option<&MissileWarning>, With<Player>>,
+    mut indicator: Query<&mut Text, With<WarningIndicator>>,
+) {
+    let Ok(mut text) = indicator.single_mut() else {
+        return;
+    };
+    match player.single() {
+        Ok(Some(warning)) => {
+            text.0 = format!(
+                "MISSILE INBOUND x{}  {:.0}m",
+                warning.inbound, warning.closest
+            );
+        }
+        _ => text.0.clear(),
+    }
+}
+
+fn update_subsystem_indicator(
+    player: Query<&SubsystemTarget, With<Player>>,
+    mut indicator: Query<&mut Text, With<SubsystemIndicator>>,
+) {
+    let Ok(mut text) = indicator.single_mut() else {
+        return;
+    };
+    match player.single().ok().and_then(|target| target.0) {
+        Some(kind) => text.0 = format!("TGT {}", kind.label()),
+        None => text.0.clear(),
+    }
+}
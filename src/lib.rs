@@ -1,7 +1,20 @@
+pub mod ai;
 pub mod combat;
 pub mod common;
 pub mod controller;
+pub mod damage;
+pub mod faction;
+pub mod hud;
+pub mod maneuver;
 pub mod reticule;
+pub mod retreat;
+pub mod shields;
+pub mod spatial;
+pub mod squadron;
+pub mod steering;
+pub mod subsystems;
+pub mod telemetry;
+pub mod tractor;
 pub mod utils;
 pub mod vfx;
 pub mod weapons;
@@ -0,0 +1,644 @@
+//! Dogfight maneuvers for AI ships.
+//!
+//! A maneuver is a short, scripted piece of flying — a strafing run, a
+//! break turn, an Immelmann — that the utility AI picks like any other
+//! action but that then runs until it's done (or aborts) rather than being
+//! re-weighed every frame. Picking one inserts its action marker;
+//! `start_maneuver` answers with a fresh `Maneuver` and
+//! `ActionStatus::Running`, which keeps the picker off the agent until
+//! `fly_maneuvers` reports it `Finished`.
+//!
+//! `Maneuver::step` is pure: given where the ship and its target are, it
+//! says which way to fly, where to point and whether to shoot, moving
+//! through its phases as it goes. Geometry that depends on how the
+//! maneuver started (e.g. which way "up" the loop goes) is fixed on the
+//! first step.
+
+use std::time::Instant;
+
+use bevy::prelude::*;
+use bevy::utils::Parallel;
+use metrics::counter;
+
+use avian3d::prelude::LinearVelocity;
+
+use crate::ai::{Action, ActionStatus, AiLod, AiLodStats, AiSet, AiTarget, PilotSkill, Scorer, UtilityAiAppExt};
+use crate::combat::{AiMarker, Mines, Staggered, SteeringContext, gather_mines};
+use crate::faction::Faction;
+use crate::steering::{self, DesiredHeading, Kinematics, SteeringLimits};
+use crate::subsystems::Subsystems;
+use crate::weapons::{FireAction, FireInput};
+
+pub struct ManeuverPlugin;
+
+impl Plugin for ManeuverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ManeuverSettings>()
+            .register_ai_scorer::<HeadOnScore>()
+            .register_ai_scorer::<OnTailScore>()
+            .register_skilled_ai_action::<StrafingRun>()
+            .register_skilled_ai_action::<BreakTurn>()
+            .register_skilled_ai_action::<BarrelRoll>()
+            .register_skilled_ai_action::<Immelmann>()
+            .register_skilled_ai_action::<Orbit>()
+            .register_skilled_ai_action::<Joust>()
+            .add_systems(PreUpdate, aspect_scorer_system.in_set(AiSet::Scorers))
+            .add_systems(
+                Update,
+                (
+                    (
+                        start_maneuver::<StrafingRun>,
+                        start_maneuver::<BreakTurn>,
+                        start_maneuver::<BarrelRoll>,
+                        start_maneuver::<Immelmann>,
+                        start_maneuver::<Orbit>,
+                        start_maneuver::<Joust>,
+                    ),
+                    ApplyDeferred,
+                    fly_maneuvers,
+                )
+                    .chain()
+                    .in_set(AiSet::Actions),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct ManeuverSettings {
+    /// Any maneuver still going after this many seconds aborts.
+    pub max_duration: f32,
+    /// Attack runs and jousts pass the target this close before pulling
+    /// through.
+    pub pass_distance: f32,
+    /// Seconds spent extending past the target after a pass.
+    pub overshoot_time: f32,
+    /// A strafing run is over once the nose is back within this many
+    /// radians of the target.
+    pub reentry_angle: f32,
+    pub break_duration: f32,
+    /// Seconds for one full barrel roll.
+    pub roll_duration: f32,
+    /// Sideways speed of the barrel roll's corkscrew, as a fraction of top
+    /// speed.
+    pub roll_radius: f32,
+    /// Seconds spent rolling out level after an Immelmann's half loop.
+    pub rollout_time: f32,
+    pub orbit_radius: f32,
+    pub orbit_duration: f32,
+}
+
+impl Default for ManeuverSettings {
+    fn default() -> Self {
+        Self {
+            max_duration: 12.0,
+            pass_distance: 8.0,
+            overshoot_time: 1.2,
+            reentry_angle: 0.5,
+            break_duration: 1.5,
+            roll_duration: 1.5,
+            roll_radius: 0.6,
+            rollout_time: 0.75,
+            orbit_radius: 15.0,
+            orbit_duration: 6.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ManeuverKind {
+    /// Dive on the target guns firing, overshoot, then come back round.
+    StrafingRun,
+    /// Hard turn towards the side the attacker is on, to spoil its angle.
+    BreakTurn,
+    /// Corkscrew around the direction of travel to throw off aim.
+    BarrelRoll,
+    /// Half loop then roll out level, reversing direction.
+    Immelmann,
+    /// Circle the target, nose on it, firing when lined up.
+    Orbit,
+    /// Fly straight at an oncoming target firing, sidestepping the pass.
+    Joust,
+}
+
+/// The stages a maneuver moves through. Most only use `Opening`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    /// The attack run, the loop, the turn.
+    Opening,
+    /// Carrying on past the target after a pass.
+    Extending,
+    /// Coming back round, or rolling out.
+    Recovering,
+}
+
+/// Where the ship and its target are this frame.
+#[derive(Clone, Copy, Debug)]
+pub struct ManeuverContext {
+    pub agent: Kinematics,
+    pub forward: Vec3,
+    pub up: Vec3,
+    pub target: Option<Kinematics>,
+    /// Where the pilot points to shoot the target, lead and aim error
+    /// included. Defaults to the target itself.
+    pub aim: Option<Vec3>,
+    /// Half-angle, in radians, the aim point must be within to open fire.
+    pub fire_cone: f32,
+    /// Furthest the aim point can be to open fire.
+    pub fire_range: f32,
+    pub limits: SteeringLimits,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ManeuverStep {
+    Fly {
+        velocity: Vec3,
+        heading: Vec3,
+        fire: bool,
+    },
+    Done,
+    Abort,
+}
+
+#[derive(Component, Debug)]
+pub struct Maneuver {
+    pub kind: ManeuverKind,
+    /// Seconds since the maneuver started.
+    pub elapsed: f32,
+    phase: Phase,
+    phase_time: f32,
+    /// The ship's forward and up when the maneuver started.
+    frame: Option<(Vec3, Vec3)>,
+    /// A direction picked at the start or at a phase change, e.g. which
+    /// way to break.
+    anchor: Vec3,
+}
+
+impl Maneuver {
+    pub fn new(kind: ManeuverKind) -> Self {
+        Self {
+            kind,
+            elapsed: 0.0,
+            phase: Phase::Opening,
+            phase_time: 0.0,
+            frame: None,
+            anchor: Vec3::ZERO,
+        }
+    }
+
+    fn enter(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.phase_time = 0.0;
+    }
+
+    /// Advances the maneuver by `dt`.
+    pub fn step(&mut self, ctx: &ManeuverContext, settings: &ManeuverSettings, dt: f32) -> ManeuverStep {
+        self.elapsed += dt;
+        self.phase_time += dt;
+        if self.elapsed > settings.max_duration {
+            return ManeuverStep::Abort;
+        }
+        let first_step = self.frame.is_none();
+        let (forward, up) = *self.frame.get_or_insert((ctx.forward, ctx.up));
+        let right = forward.cross(up).normalize_or_zero();
+        let speed = ctx.limits.max_speed;
+        let position = ctx.agent.position;
+        let travel = ctx.agent.velocity.try_normalize().unwrap_or(ctx.forward);
+        let aim = ctx.aim.or(ctx.target.map(|target| target.position));
+        // Where to point while attacking, and whether to shoot from there.
+        let guns = || {
+            let offset = aim.map_or(Vec3::ZERO, |aim| aim - position);
+            let firing = offset.length() <= ctx.fire_range
+                && ctx.forward.angle_between(offset) <= ctx.fire_cone;
+            (offset, firing)
+        };
+        let fly = |direction: Vec3, heading: Vec3, fire: bool| ManeuverStep::Fly {
+            velocity: direction.normalize_or_zero() * speed,
+            heading,
+            fire,
+        };
+
+        match self.kind {
+            ManeuverKind::StrafingRun => {
+                let Some(target) = ctx.target else {
+                    return ManeuverStep::Abort;
+                };
+                let offset = target.position - position;
+                match self.phase {
+                    Phase::Opening => {
+                        if offset.length() < settings.pass_distance {
+                            self.enter(Phase::Extending);
+                        }
+                        let (nose, firing) = guns();
+                        fly(offset, nose, firing)
+                    }
+                    Phase::Extending => {
+                        if self.phase_time >= settings.overshoot_time {
+                            self.enter(Phase::Recovering);
+                        }
+                        fly(travel, travel, false)
+                    }
+                    Phase::Recovering => {
+                        if ctx.forward.angle_between(offset) <= settings.reentry_angle {
+                            return ManeuverStep::Done;
+                        }
+                        fly(offset, offset, false)
+                    }
+                }
+            }
+            ManeuverKind::BreakTurn => {
+                if first_step {
+                    // Turn towards whichever side the attacker is on.
+                    let lateral = ctx.target.map_or(Vec3::ZERO, |target| {
+                        (target.position - position).reject_from_normalized(forward)
+                    });
+                    self.anchor = lateral.try_normalize().unwrap_or(right);
+                }
+                if self.phase_time >= settings.break_duration {
+                    return ManeuverStep::Done;
+                }
+                fly(self.anchor, self.anchor, false)
+            }
+            ManeuverKind::BarrelRoll => {
+                if self.phase_time >= settings.roll_duration {
+                    return ManeuverStep::Done;
+                }
+                let angle = std::f32::consts::TAU * self.phase_time / settings.roll_duration;
+                let around = right * angle.cos() + up * angle.sin();
+                let velocity = forward * (1.0 - settings.roll_radius) + around * settings.roll_radius;
+                fly(velocity, velocity, false)
+            }
+            ManeuverKind::Immelmann => match self.phase {
+                Phase::Recovering => {
+                    if self.phase_time >= settings.rollout_time {
+                        return ManeuverStep::Done;
+                    }
+                    // `turn_towards` levels the wings on its own.
+                    fly(-forward, -forward, false)
+                }
+                _ => {
+                    let angle = (self.phase_time * ctx.limits.max_turn_rate).min(std::f32::consts::PI);
+                    if angle >= std::f32::consts::PI {
+                        self.enter(Phase::Recovering);
+                    }
+                    let direction = forward * angle.cos() + up * angle.sin();
+                    fly(direction, direction, false)
+                }
+            },
+            ManeuverKind::Orbit => {
+                let Some(target) = ctx.target else {
+                    return ManeuverStep::Abort;
+                };
+                let radial = position - target.position;
+                let distance = radial.length();
+                if distance > settings.orbit_radius * 3.0 {
+                    return ManeuverStep::Abort;
+                }
+                if self.phase_time >= settings.orbit_duration {
+                    return ManeuverStep::Done;
+                }
+                // Circle in the plane the ship started level in, easing
+                // in or out towards the orbit radius.
+                let tangent = up.cross(radial).normalize_or_zero();
+                let correction = -radial.normalize_or_zero()
+                    * ((distance - settings.orbit_radius) / settings.orbit_radius).clamp(-1.0, 1.0);
+                let velocity = target.velocity + (tangent + correction).normalize_or_zero() * speed;
+                let (nose, fire) = guns();
+                ManeuverStep::Fly {
+                    velocity,
+                    heading: nose,
+                    fire,
+                }
+            }
+            ManeuverKind::Joust => {
+                let Some(target) = ctx.target else {
+                    return ManeuverStep::Abort;
+                };
+                let offset = target.position - position;
+                match self.phase {
+                    Phase::Opening => {
+                        // Only a joust while the target keeps coming.
+                        if target.velocity.dot(offset) > 0.0 {
+                            return ManeuverStep::Abort;
+                        }
+                        if offset.length() < settings.pass_distance {
+                            self.anchor = right;
+                            self.enter(Phase::Extending);
+                        }
+                        let (nose, firing) = guns();
+                        fly(offset, nose, firing)
+                    }
+                    _ => {
+                        if self.phase_time >= settings.overshoot_time {
+                            return ManeuverStep::Done;
+                        }
+                        // Sidestep so the pass isn't a collision.
+                        let direction = travel + self.anchor * 0.5;
+                        fly(direction, direction, false)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An action that flies a maneuver.
+pub trait ManeuverAction: Action {
+    const KIND: ManeuverKind;
+}
+
+#[derive(Component, Default)]
+pub struct StrafingRun;
+
+impl Action for StrafingRun {
+    const NAME: &'static str = "strafing_run";
+}
+
+impl ManeuverAction for StrafingRun {
+    const KIND: ManeuverKind = ManeuverKind::StrafingRun;
+}
+
+#[derive(Component, Default)]
+pub struct BreakTurn;
+
+impl Action for BreakTurn {
+    const NAME: &'static str = "break_turn";
+}
+
+impl ManeuverAction for BreakTurn {
+    const KIND: ManeuverKind = ManeuverKind::BreakTurn;
+}
+
+#[derive(Component, Default)]
+pub struct BarrelRoll;
+
+impl Action for BarrelRoll {
+    const NAME: &'static str = "barrel_roll";
+}
+
+impl ManeuverAction for BarrelRoll {
+    const KIND: ManeuverKind = ManeuverKind::BarrelRoll;
+}
+
+#[derive(Component, Default)]
+pub struct Immelmann;
+
+impl Action for Immelmann {
+    const NAME: &'static str = "immelmann";
+}
+
+impl ManeuverAction for Immelmann {
+    const KIND: ManeuverKind = ManeuverKind::Immelmann;
+}
+
+#[derive(Component, Default)]
+pub struct Orbit;
+
+impl Action for Orbit {
+    const NAME: &'static str = "orbit";
+}
+
+impl ManeuverAction for Orbit {
+    const KIND: ManeuverKind = ManeuverKind::Orbit;
+}
+
+#[derive(Component, Default)]
+pub struct Joust;
+
+impl Action for Joust {
+    const NAME: &'static str = "joust";
+}
+
+impl ManeuverAction for Joust {
+    const KIND: ManeuverKind = ManeuverKind::Joust;
+}
+
+/// How squarely the agent and its target are flying at each other.
+#[derive(Component, Default)]
+pub struct HeadOnScore(f32);
+
+impl Scorer for HeadOnScore {
+    const NAME: &'static str = "head_on";
+
+    fn value(&self) -> f32 {
+        self.0
+    }
+}
+
+/// How squarely the target sits behind the agent flying at it.
+#[derive(Component, Default)]
+pub struct OnTailScore(f32);
+
+impl Scorer for OnTailScore {
+    const NAME: &'static str = "on_tail";
+
+    fn value(&self) -> f32 {
+        self.0
+    }
+}
+
+/// The target's own heading isn't tracked, so its velocity stands in for
+/// where its nose points.
+#[allow(clippy::type_complexity)]
+fn aspect_scorer_system(
+    mut query: Query<(&Transform, &AiTarget, Option<&AiLod>, &mut HeadOnScore, &mut OnTailScore), With<AiMarker>>,
+    mut stats: ResMut<AiLodStats>,
+    mut local_stats: Local<Parallel<AiLodStats>>,
+) {
+    query.par_iter_mut().for_each(|(transform, target, lod, mut head_on, mut on_tail)| {
+        if lod.is_some_and(|lod| !lod.thinking()) {
+            return;
+        }
+        let started = Instant::now();
+        (head_on.0, on_tail.0) = match target.last_known_position.filter(|_| target.in_contact) {
+            Some(position) => {
+                let to_target = (position - transform.translation).normalize_or_zero();
+                let ahead = transform.forward().dot(to_target);
+                let coming = target.last_known_velocity.normalize_or_zero().dot(-to_target).max(0.0);
+                (ahead.max(0.0) * coming, (-ahead).max(0.0) * coming)
+            }
+            None => (0.0, 0.0),
+        };
+        if let Some(lod) = lod {
+            local_stats.scope(|stats| stats.record(lod.tier, started));
+        }
+    });
+    stats.absorb(&mut local_stats);
+}
+
+/// Starts a fresh maneuver whenever the picker inserts `A`.
+fn start_maneuver<A: ManeuverAction>(mut commands: Commands, agents: Query<Entity, Added<A>>) {
+    for entity in &agents {
+        commands
+            .entity(entity)
+            .insert((Maneuver::new(A::KIND), ActionStatus::Running));
+    }
+}
+
+type Maneuvering<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Faction,
+        &'static AiTarget,
+        &'static Transform,
+        &'static mut LinearVelocity,
+        &'static mut DesiredHeading,
+        &'static mut Maneuver,
+        Option<&'static mut FireInput>,
+        Option<&'static Subsystems>,
+        Option<&'static SteeringLimits>,
+        Option<&'static PilotSkill>,
+    ),
+    (With<AiMarker>, Without<Staggered>),
+>;
+
+fn fly_maneuvers(
+    mut commands: Commands,
+    mut agents: ParamSet<(Maneuvering, Mines)>,
+    settings: Res<ManeuverSettings>,
+    steering: SteeringContext,
+) {
+    let mines = gather_mines(&agents.p1());
+    let dt = steering.time.delta_secs();
+
+    for (entity, faction, target, transform, mut linvel, mut heading, mut maneuver, fire, subsystems, limits, skill) in
+        &mut agents.p0()
+    {
+        let limits = steering.limits(limits, subsystems);
+        let agent = Kinematics {
+            position: transform.translation,
+            velocity: linvel.0,
+        };
+        let ctx = ManeuverContext {
+            agent,
+            forward: *transform.forward(),
+            up: *transform.up(),
+            target: target.last_known_position.map(|position| Kinematics {
+                position,
+                velocity: target.last_known_velocity,
+            }),
+            aim: target.last_known_position.map(|position| {
+                steering.aim(entity, skill, agent.position, position, target.last_known_velocity)
+            }),
+            fire_cone: steering.fire_cone(skill),
+            fire_range: steering.settings.fire_range,
+            limits,
+        };
+
+        let step = maneuver.step(&ctx, &settings, dt);
+        let firing = match step {
+            ManeuverStep::Fly { velocity, heading: nose, fire } => {
+                let acceleration = steering
+                    .hazards(entity, *faction, &agent, &mines, limits)
+                    .add(velocity - agent.velocity, 1.0)
+                    .finish(limits);
+                linvel.0 = steering::integrate(linvel.0, acceleration, dt, limits);
+                heading.0 = Some(nose);
+                Some(fire)
+            }
+            ManeuverStep::Done | ManeuverStep::Abort => {
+                let outcome = if step == ManeuverStep::Done { "done" } else { "abort" };
+                counter!("ai.maneuvers", "outcome" => outcome).increment(1);
+                commands
+                    .entity(entity)
+                    .remove::<Maneuver>()
+                    .insert(ActionStatus::Finished);
+                // `hold_fire` lets go once the maneuver is gone.
+                None
+            }
+        };
+        if let (Some(mut input), Some(firing)) = (fire, firing) {
+            input.set(FireAction::Primary, firing);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::combat::CombatSettings;
+    use crate::weapons::{Projectile, WeaponLoadout, fire_weapons};
+
+    fn context(target: Option<Vec3>) -> ManeuverContext {
+        ManeuverContext {
+            agent: Kinematics {
+                position: Vec3::ZERO,
+                velocity: Vec3::NEG_Z * 10.0,
+            },
+            forward: Vec3::NEG_Z,
+            up: Vec3::Y,
+            target: target.map(|position| Kinematics {
+                position,
+                velocity: Vec3::ZERO,
+            }),
+            aim: None,
+            fire_cone: CombatSettings::default().fire_cone,
+            fire_range: CombatSettings::default().fire_range,
+            limits: SteeringLimits::default(),
+        }
+    }
+
+    #[test]
+    fn strafing_run_fires_passes_and_aborts_without_a_target() {
+        let settings = ManeuverSettings::default();
+        let mut run = Maneuver::new(ManeuverKind::StrafingRun);
+        let ahead = context(Some(Vec3::NEG_Z * 30.0));
+        assert!(matches!(run.step(&ahead, &settings, 0.1), ManeuverStep::Fly { fire: true, .. }));
+        run.step(&context(Some(Vec3::NEG_Z * 2.0)), &settings, 0.1);
+        assert_eq!(run.phase, Phase::Extending);
+        assert_eq!(run.step(&context(None), &settings, 0.1), ManeuverStep::Abort);
+    }
+
+    #[test]
+    fn immelmann_reverses_and_finishes() {
+        let settings = ManeuverSettings::default();
+        let mut immelmann = Maneuver::new(ManeuverKind::Immelmann);
+        let ctx = context(None);
+        let mut last = None;
+        for _ in 0..200 {
+            match immelmann.step(&ctx, &settings, 0.05) {
+                ManeuverStep::Fly { velocity, .. } => last = Some(velocity),
+                step => {
+                    assert_eq!(step, ManeuverStep::Done);
+                    assert!(last.unwrap().z > 0.0, "should end flying the other way");
+                    return;
+                }
+            }
+        }
+        panic!("never finished");
+    }
+
+    #[test]
+    fn strafing_run_pulls_the_trigger_and_the_guns_fire() {
+        let mut world = crate::combat::test_world();
+        world.init_resource::<ManeuverSettings>();
+        let target = world.spawn(Transform::from_xyz(0.0, 0.0, -30.0)).id();
+        let mut ai_target = AiTarget::default();
+        ai_target.assign(target, Vec3::new(0.0, 0.0, -30.0), Vec3::ZERO, true);
+        let agent = world
+            .spawn((
+                AiMarker,
+                Faction::Raiders,
+                ai_target,
+                Transform::default(),
+                LinearVelocity::default(),
+                Maneuver::new(ManeuverKind::StrafingRun),
+                FireInput::default(),
+                WeaponLoadout::default(),
+            ))
+            .id();
+
+        world.run_system_once(fly_maneuvers).unwrap();
+        let input = world.get::<FireInput>(agent).unwrap();
+        assert!(input.trigger(FireAction::Primary).pressed);
+
+        world.run_system_once(fire_weapons).unwrap();
+        let mut projectiles = world.query::<&Projectile>();
+        let shots: Vec<_> = projectiles.iter(&world).collect();
+        assert_eq!(shots.len(), 1);
+        assert_eq!(shots[0].owner, agent);
+    }
+}
@@ -0,0 +1,408 @@
+//! Evading, retreating and coming back.
+//!
+//! Picking `Evade` starts a `Retreat` that runs to completion like a
+//! maneuver (see `maneuver`), through up to three phases:
+//!
+//! - `BreakOff`: run from the threat, jinking side to side so it can't
+//!   line up a shot, until clear of it or `break_duration` is up.
+//! - `Regroup`: fly to the nearest friendly `RallyPoint`, or failing that
+//!   the middle of whatever friendlies are about.
+//! - `Repair`: hold there, and once nothing has hit the ship for
+//!   `calm_time`, patch the hull up at `repair_rate`.
+//!
+//! How hurt a pilot has to be to go further than breaking off, and how
+//! healed before re-engaging, comes from its `PilotSkill::courage`. A
+//! threat closing in again sends a regrouping or repairing ship back to
+//! breaking off. However it ends, the retreat reports
+//! `ActionStatus::Finished` and the picker takes it from there.
+
+use std::f32::consts::TAU;
+
+use bevy::platform::collections::HashSet;
+use bevy::prelude::*;
+use metrics::counter;
+
+use avian3d::prelude::LinearVelocity;
+
+use crate::ai::{ActionStatus, AiSet, PilotSkill};
+use crate::combat::{ActingAgents, Evade, Mines, Ship, ShipDamaged, SteeringContext, gather_mines};
+use crate::faction::Faction;
+use crate::steering::{self, Kinematics, SteeringLimits};
+
+pub struct RetreatPlugin;
+
+impl Plugin for RetreatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RetreatSettings>()
+            .register_type::<RallyPoint>()
+            .add_systems(
+                Update,
+                (start_retreat, ApplyDeferred, fly_retreats)
+                    .chain()
+                    .in_set(AiSet::Actions),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct RetreatSettings {
+    /// Longest a break-off lasts.
+    pub break_duration: f32,
+    /// A threat further off than this has been left behind.
+    pub safe_distance: f32,
+    /// Sideways acceleration of the jink, as a fraction of the ship's.
+    pub jink_strength: f32,
+    /// Jinks per second.
+    pub jink_rate: f32,
+    /// How far to look for friendlies to regroup with, absent a rally point.
+    pub ally_radius: f32,
+    /// Close enough to the rally to start repairs.
+    pub rally_radius: f32,
+    /// Ships start easing off this far out from the rally.
+    pub slowing_radius: f32,
+    /// Seconds spent regrouping before repairing wherever the ship is.
+    pub regroup_time: f32,
+    /// Seconds without being hit before repairs start.
+    pub calm_time: f32,
+    /// Hull repaired per second, as a fraction of max health.
+    pub repair_rate: f32,
+}
+
+impl Default for RetreatSettings {
+    fn default() -> Self {
+        Self {
+            break_duration: 3.0,
+            safe_distance: 60.0,
+            jink_strength: 0.7,
+            jink_rate: 0.8,
+            ally_radius: 150.0,
+            rally_radius: 10.0,
+            slowing_radius: 20.0,
+            regroup_time: 20.0,
+            calm_time: 2.0,
+            repair_rate: 0.05,
+        }
+    }
+}
+
+/// Somewhere ships of `faction` fall back to, e.g. a carrier.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct RallyPoint {
+    pub faction: Faction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetreatPhase {
+    BreakOff,
+    Regroup,
+    Repair,
+}
+
+/// A retreat in progress.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Retreat {
+    pub phase: RetreatPhase,
+    /// Seconds in the current phase.
+    pub phase_time: f32,
+    /// Hurt enough to regroup and repair rather than just break off.
+    pub full: bool,
+    /// Seconds since the ship was last hit.
+    pub calm_for: f32,
+    /// Offsets the jink so ships breaking off together don't move as one.
+    seed: f32,
+}
+
+impl Retreat {
+    pub fn new(seed: f32) -> Self {
+        Self {
+            phase: RetreatPhase::BreakOff,
+            phase_time: 0.0,
+            full: false,
+            calm_for: 0.0,
+            seed,
+        }
+    }
+
+    fn enter(&mut self, phase: RetreatPhase) {
+        self.phase = phase;
+        self.phase_time = 0.0;
+    }
+
+    /// Advances the retreat by `dt` and says how to fly.
+    pub fn step(&mut self, ctx: &RetreatContext, settings: &RetreatSettings, dt: f32) -> RetreatStep {
+        self.phase_time += dt;
+        self.calm_for = if ctx.hit { 0.0 } else { self.calm_for + dt };
+        self.full |= ctx.health <= ctx.retreat_health;
+        let agent = &ctx.agent;
+        let threatened = ctx.threat_in_contact
+            && ctx
+                .threat
+                .is_some_and(|threat| threat.position.distance(agent.position) < settings.safe_distance);
+
+        match self.phase {
+            RetreatPhase::BreakOff => {
+                if !threatened || self.phase_time >= settings.break_duration {
+                    if !self.full {
+                        return RetreatStep::Done;
+                    }
+                    self.enter(RetreatPhase::Regroup);
+                }
+            }
+            RetreatPhase::Regroup | RetreatPhase::Repair if threatened => self.enter(RetreatPhase::BreakOff),
+            RetreatPhase::Regroup => {
+                let arrived = ctx
+                    .rally
+                    .is_none_or(|rally| rally.position.distance(agent.position) <= settings.rally_radius);
+                if arrived || self.phase_time >= settings.regroup_time {
+                    self.enter(RetreatPhase::Repair);
+                }
+            }
+            RetreatPhase::Repair => {
+                if ctx.health >= ctx.reengage_health {
+                    return RetreatStep::Done;
+                }
+            }
+        }
+
+        let acceleration = match self.phase {
+            RetreatPhase::BreakOff => ctx.threat.map_or(Vec3::ZERO, |threat| {
+                let flee = steering::evade(
+                    agent,
+                    threat.position,
+                    threat.velocity,
+                    ctx.limits,
+                    ctx.max_prediction,
+                );
+                let away = (agent.position - threat.position).normalize_or(Vec3::NEG_Z);
+                let (side, up) = away.any_orthonormal_pair();
+                let t = self.phase_time * settings.jink_rate * TAU + self.seed;
+                let jink = (side * t.sin() + up * (0.7 * t).cos()) * ctx.limits.max_acceleration * settings.jink_strength;
+                flee + jink
+            }),
+            // Without anywhere to go, slow down where the ship is.
+            RetreatPhase::Regroup | RetreatPhase::Repair => {
+                let rally = ctx.rally.map_or(agent.position, |rally| rally.position);
+                steering::arrive(agent, rally, settings.slowing_radius, ctx.limits)
+            }
+        };
+        RetreatStep::Fly {
+            acceleration,
+            repair: self.phase == RetreatPhase::Repair && self.calm_for >= settings.calm_time,
+        }
+    }
+}
+
+/// What a retreating ship knows this frame.
+#[derive(Clone, Copy, Debug)]
+pub struct RetreatContext {
+    pub agent: Kinematics,
+    /// Where the threat is, or was last seen.
+    pub threat: Option<Kinematics>,
+    pub threat_in_contact: bool,
+    pub rally: Option<Kinematics>,
+    /// Hit this frame.
+    pub hit: bool,
+    /// Fraction of max health left.
+    pub health: f32,
+    pub retreat_health: f32,
+    pub reengage_health: f32,
+    pub limits: SteeringLimits,
+    /// Furthest ahead, in seconds, to lead the threat when fleeing it.
+    pub max_prediction: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RetreatStep {
+    Fly { acceleration: Vec3, repair: bool },
+    Done,
+}
+
+fn start_retreat(mut commands: Commands, agents: Query<Entity, Added<Evade>>) {
+    for entity in &agents {
+        commands
+            .entity(entity)
+            .insert((Retreat::new(entity.index_u32() as f32), ActionStatus::Running));
+    }
+}
+
+type RallyPoints<'w, 's> =
+    Query<'w, 's, (&'static RallyPoint, &'static Transform, Option<&'static LinearVelocity>)>;
+
+fn fly_retreats(
+    mut commands: Commands,
+    mut agents: ParamSet<(ActingAgents<Evade>, Mines, RallyPoints)>,
+    mut retreats: Query<(&mut Retreat, &mut Ship)>,
+    mut damaged: MessageReader<ShipDamaged>,
+    settings: Res<RetreatSettings>,
+    steering: SteeringContext,
+) {
+    let mines = gather_mines(&agents.p1());
+    let rally_points: Vec<(Faction, Kinematics)> = agents
+        .p2()
+        .iter()
+        .map(|(rally, transform, velocity)| {
+            let kinematics = Kinematics {
+                position: transform.translation,
+                velocity: velocity.map_or(Vec3::ZERO, |velocity| velocity.0),
+            };
+            (rally.faction, kinematics)
+        })
+        .collect();
+    let dt = steering.time.delta_secs();
+    let hit: HashSet<Entity> = damaged.read().map(|event| event.ship).collect();
+    let average = PilotSkill::default();
+
+    for (entity, faction, target, transform, mut linvel, mut heading, subsystems, limits, skill) in &mut agents.p0() {
+        let Ok((mut retreat, mut ship)) = retreats.get_mut(entity) else {
+            continue;
+        };
+        let agent = Kinematics {
+            position: transform.translation,
+            velocity: linvel.0,
+        };
+        let skill = skill.unwrap_or(&average);
+        let ctx = RetreatContext {
+            agent,
+            threat: target.last_known_position.map(|position| Kinematics {
+                position,
+                velocity: target.last_known_velocity,
+            }),
+            threat_in_contact: target.in_contact,
+            rally: rally(entity, *faction, agent.position, &rally_points, &steering, &settings),
+            hit: hit.contains(&entity),
+            health: ship.health / ship.max_health.max(1.0),
+            retreat_health: skill.retreat_health(),
+            reengage_health: skill.reengage_health(),
+            // Engines pushed past their usual limits while breaking off.
+            limits: match retreat.phase {
+                RetreatPhase::BreakOff => steering.limits(limits, subsystems).scaled(steering.settings.evade_boost),
+                _ => steering.limits(limits, subsystems),
+            },
+            max_prediction: steering.settings.max_prediction,
+        };
+
+        match retreat.step(&ctx, &settings, dt) {
+            RetreatStep::Fly { acceleration, repair } => {
+                let acceleration = steering
+                    .hazards(entity, *faction, &agent, &mines, ctx.limits)
+                    .add(acceleration, 1.0)
+                    .finish(ctx.limits);
+                linvel.0 = steering::integrate(linvel.0, acceleration, dt, ctx.limits);
+                heading.0 = linvel.0.try_normalize();
+                if repair {
+                    ship.health = (ship.health + settings.repair_rate * ship.max_health * dt).min(ship.max_health);
+                }
+            }
+            RetreatStep::Done => {
+                let outcome = if retreat.full { "repaired" } else { "broke_off" };
+                counter!("ai.retreats", "outcome" => outcome).increment(1);
+                commands
+                    .entity(entity)
+                    .remove::<Retreat>()
+                    .insert(ActionStatus::Finished);
+            }
+        }
+    }
+}
+
+/// Where `entity` falls back to: its faction's nearest rally point, else
+/// the middle of the friendlies around it.
+fn rally(
+    entity: Entity,
+    faction: Faction,
+    position: Vec3,
+    rally_points: &[(Faction, Kinematics)],
+    steering: &SteeringContext,
+    settings: &RetreatSettings,
+) -> Option<Kinematics> {
+    let nearest = rally_points
+        .iter()
+        .filter(|(owner, _)| *owner == faction)
+        .map(|(_, rally)| *rally)
+        .min_by(|a, b| {
+            a.position
+                .distance_squared(position)
+                .total_cmp(&b.position.distance_squared(position))
+        });
+    if nearest.is_some() {
+        return nearest;
+    }
+
+    let (sum, count) = steering
+        .index
+        .within_radius(position, settings.ally_radius)
+        .filter(|(entry, _)| entry.entity != entity && entry.faction == faction)
+        .fold((Vec3::ZERO, 0), |(sum, count), (entry, _)| (sum + entry.position, count + 1));
+    (count > 0).then(|| Kinematics {
+        position: sum / count as f32,
+        velocity: Vec3::ZERO,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(health: f32, threat_distance: f32) -> RetreatContext {
+        RetreatContext {
+            agent: Kinematics {
+                position: Vec3::ZERO,
+                velocity: Vec3::ZERO,
+            },
+            threat: Some(Kinematics {
+                position: Vec3::NEG_Z * threat_distance,
+                velocity: Vec3::ZERO,
+            }),
+            threat_in_contact: true,
+            rally: Some(Kinematics {
+                position: Vec3::X * 5.0,
+                velocity: Vec3::ZERO,
+            }),
+            hit: false,
+            health,
+            retreat_health: 0.35,
+            reengage_health: 0.75,
+            limits: SteeringLimits::default(),
+            max_prediction: 2.0,
+        }
+    }
+
+    #[test]
+    fn a_healthy_pilot_only_breaks_off() {
+        let settings = RetreatSettings::default();
+        let mut retreat = Retreat::new(0.0);
+        let RetreatStep::Fly { acceleration, .. } = retreat.step(&context(0.9, 20.0), &settings, 0.1) else {
+            panic!("should be breaking off");
+        };
+        // Away from the threat, ahead of it.
+        assert!(acceleration.z > 0.0);
+        assert_eq!(retreat.step(&context(0.9, 100.0), &settings, 0.1), RetreatStep::Done);
+    }
+
+    #[test]
+    fn a_hurt_pilot_regroups_repairs_and_reengages() {
+        let settings = RetreatSettings::default();
+        let mut retreat = Retreat::new(0.0);
+        retreat.step(&context(0.2, 20.0), &settings, 0.1);
+        assert!(retreat.full);
+        // Clear of the threat: off to the rally point, already in reach.
+        retreat.step(&context(0.2, 100.0), &settings, 0.1);
+        assert_eq!(retreat.phase, RetreatPhase::Regroup);
+        retreat.step(&context(0.2, 100.0), &settings, 0.1);
+        assert_eq!(retreat.phase, RetreatPhase::Repair);
+        let mut repairing = false;
+        for _ in 0..30 {
+            if let RetreatStep::Fly { repair, .. } = retreat.step(&context(0.2, 100.0), &settings, 0.1) {
+                repairing = repair;
+            }
+        }
+        assert!(repairing);
+        // The threat coming back sends it running again.
+        retreat.step(&context(0.5, 20.0), &settings, 0.1);
+        assert_eq!(retreat.phase, RetreatPhase::BreakOff);
+        retreat.step(&context(0.8, 100.0), &settings, 0.1);
+        retreat.step(&context(0.8, 100.0), &settings, 0.1);
+        assert_eq!(retreat.step(&context(0.8, 100.0), &settings, 0.1), RetreatStep::Done);
+    }
+}
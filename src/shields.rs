@@ -0,0 +1,147 @@
+//! Regenerating shields that soak damage before it reaches `Ship::health`.
+//!
+//! A shield is either a single bubble or split into front and rear facings,
+//! chosen per hit by which side of the ship the shot came from. Absorption
+//! itself happens in `weapons`' hit resolution; this module owns the
+//! component, the facing/absorb math, and regeneration after a quiet
+//! period, slowed by damage to the ship's shield generator.
+
+use bevy::prelude::*;
+
+use crate::subsystems::{SubsystemKind, Subsystems, subsystem_efficiency};
+
+pub struct ShieldsPlugin;
+
+impl Plugin for ShieldsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, regenerate_shields);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShieldFacing {
+    Front,
+    Rear,
+}
+
+#[derive(Component, Debug)]
+pub struct Shield {
+    /// Maximum strength of each facing.
+    pub capacity: f32,
+    /// Current strength, front then rear. A non-directional shield only
+    /// uses the front entry.
+    pub strength: [f32; 2],
+    pub directional: bool,
+    /// Strength regained per second per facing, once regeneration starts.
+    pub regen_rate: f32,
+    /// Seconds without taking a hit before regeneration starts.
+    pub regen_delay: f32,
+    since_hit: f32,
+}
+
+impl Shield {
+    pub fn new(capacity: f32, regen_rate: f32, regen_delay: f32) -> Self {
+        Self {
+            capacity,
+            strength: [capacity, 0.0],
+            directional: false,
+            regen_rate,
+            regen_delay,
+            since_hit: 0.0,
+        }
+    }
+
+    pub fn directional(capacity: f32, regen_rate: f32, regen_delay: f32) -> Self {
+        Self {
+            strength: [capacity, capacity],
+            directional: true,
+            ..Self::new(capacity, regen_rate, regen_delay)
+        }
+    }
+
+    /// Which facing takes a hit arriving from `source_direction` (world
+    /// space, pointing from the ship towards where the hit came from).
+    pub fn facing_for(&self, ship_rotation: Quat, source_direction: Vec3) -> ShieldFacing {
+        if !self.directional {
+            return ShieldFacing::Front;
+        }
+        let local = ship_rotation.inverse() * source_direction;
+        // Ship-local forward is -Z.
+        if local.z <= 0.0 {
+            ShieldFacing::Front
+        } else {
+            ShieldFacing::Rear
+        }
+    }
+
+    pub fn strength(&self, facing: ShieldFacing) -> f32 {
+        self.strength[facing as usize]
+    }
+
+    /// Soaks up to `amount` of shield damage on `facing` and restarts the
+    /// regeneration delay. Returns the fraction of the hit (0..=1) that got
+    /// through to the hull.
+    pub fn absorb(&mut self, facing: ShieldFacing, amount: f32) -> f32 {
+        self.since_hit = 0.0;
+        if amount <= 0.0 {
+            return 0.0;
+        }
+        let strength = &mut self.strength[facing as usize];
+        let absorbed = amount.min(*strength);
+        *strength -= absorbed;
+        (amount - absorbed) / amount
+    }
+
+    /// `rate_scale` multiplies `regen_rate`, e.g. for a damaged generator.
+    fn regenerate(&mut self, dt: f32, rate_scale: f32) {
+        self.since_hit += dt;
+        if self.since_hit < self.regen_delay {
+            return;
+        }
+        let facings = if self.directional { 2 } else { 1 };
+        let amount = self.regen_rate * rate_scale * dt;
+        for strength in &mut self.strength[..facings] {
+            *strength = (*strength + amount).min(self.capacity);
+        }
+    }
+}
+
+fn regenerate_shields(time: Res<Time>, mut shields: Query<(&mut Shield, Option<&Subsystems>)>) {
+    let dt = time.delta_secs();
+    for (mut shield, subsystems) in &mut shields {
+        shield.regenerate(dt, subsystem_efficiency(subsystems, SubsystemKind::ShieldGenerator));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overflow_passes_through_to_hull() {
+        let mut shield = Shield::new(10.0, 5.0, 2.0);
+        assert_eq!(shield.absorb(ShieldFacing::Front, 4.0), 0.0);
+        assert_eq!(shield.strength(ShieldFacing::Front), 6.0);
+        let through = shield.absorb(ShieldFacing::Front, 12.0);
+        assert!((through - 0.5).abs() < 1e-5);
+        assert_eq!(shield.strength(ShieldFacing::Front), 0.0);
+    }
+
+    #[test]
+    fn directional_facing_follows_hit_side() {
+        let shield = Shield::directional(10.0, 5.0, 2.0);
+        let rotation = Quat::IDENTITY;
+        assert_eq!(shield.facing_for(rotation, Vec3::NEG_Z), ShieldFacing::Front);
+        assert_eq!(shield.facing_for(rotation, Vec3::Z), ShieldFacing::Rear);
+    }
+
+    #[test]
+    fn regeneration_waits_for_delay() {
+        let mut shield = Shield::new(10.0, 5.0, 2.0);
+        shield.absorb(ShieldFacing::Front, 10.0);
+        shield.regenerate(1.0, 1.0);
+        assert_eq!(shield.strength(ShieldFacing::Front), 0.0);
+        shield.regenerate(1.5, 1.0);
+        assert!(shield.strength(ShieldFacing::Front) > 0.0);
+    }
+}
@@ -0,0 +1,253 @@
+//! 3D spatial index over every entity with a `Faction`.
+//!
+//! `SpatialIndex` is a uniform hash grid rebuilt from scratch each frame in
+//! `SpatialSet` (`PreUpdate`): entries are bucketed by cell and sorted so
+//! each occupied cell is one contiguous slice. At a few thousand ships a
+//! rebuild is cheaper than tracking moves incrementally, and it keeps the
+//! index trivially consistent with despawns. Radius, k-nearest and cone
+//! queries replace the all-pairs scans the AI and missile lock used to do;
+//! see `benches/spatial.rs` for how they scale.
+
+use std::ops::Range;
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use metrics::gauge;
+
+use crate::faction::Faction;
+
+pub struct SpatialPlugin;
+
+impl Plugin for SpatialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialIndex>()
+            .add_systems(PreUpdate, rebuild_spatial_index.in_set(SpatialSet));
+    }
+}
+
+/// Runs the index rebuild. Anything querying `SpatialIndex` in `PreUpdate`
+/// should order itself after this.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpatialSet;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpatialEntry {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub faction: Faction,
+}
+
+#[derive(Resource, Debug)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    /// Sorted by cell.
+    entries: Vec<SpatialEntry>,
+    cells: HashMap<IVec3, Range<usize>>,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self::new(20.0)
+    }
+}
+
+impl SpatialIndex {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            entries: Vec::new(),
+            cells: HashMap::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn cell(&self, position: Vec3) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+
+    /// Replaces the contents of the index.
+    pub fn rebuild(&mut self, entries: impl IntoIterator<Item = SpatialEntry>) {
+        self.entries.clear();
+        self.entries.extend(entries);
+        let cell_size = self.cell_size;
+        let cell = |entry: &SpatialEntry| {
+            let cell = (entry.position / cell_size).floor().as_ivec3();
+            (cell.x, cell.y, cell.z)
+        };
+        self.entries.sort_unstable_by_key(cell);
+
+        self.cells.clear();
+        let mut start = 0;
+        while start < self.entries.len() {
+            let key = cell(&self.entries[start]);
+            let end = start
+                + self.entries[start..]
+                    .iter()
+                    .position(|entry| cell(entry) != key)
+                    .unwrap_or(self.entries.len() - start);
+            self.cells.insert(IVec3::new(key.0, key.1, key.2), start..end);
+            start = end;
+        }
+    }
+
+    /// Every entry within `radius` of `center`, with its distance.
+    pub fn within_radius(
+        &self,
+        center: Vec3,
+        radius: f32,
+    ) -> impl Iterator<Item = (&SpatialEntry, f32)> + '_ {
+        let min = self.cell(center - Vec3::splat(radius));
+        let max = self.cell(center + Vec3::splat(radius));
+        let span = (max - min + IVec3::ONE).as_i64vec3();
+        // A large radius over a sparse grid touches more cells than are
+        // occupied; walk the occupied ones instead.
+        let ranges: Vec<Range<usize>> = if span.x * span.y * span.z > self.cells.len() as i64 {
+            self.cells
+                .iter()
+                .filter(|(cell, _)| cell.cmpge(min).all() && cell.cmple(max).all())
+                .map(|(_, range)| range.clone())
+                .collect()
+        } else {
+            (min.x..=max.x)
+                .flat_map(|x| (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| IVec3::new(x, y, z))))
+                .filter_map(|cell| self.cells.get(&cell).cloned())
+                .collect()
+        };
+        ranges
+            .into_iter()
+            .flat_map(|range| &self.entries[range])
+            .filter_map(move |entry| {
+                let distance = entry.position.distance(center);
+                (distance <= radius).then_some((entry, distance))
+            })
+    }
+
+    /// Up to `k` entries passing `filter` closest to `center`, nearest
+    /// first, searching no further than `max_radius`.
+    pub fn nearest(
+        &self,
+        center: Vec3,
+        k: usize,
+        max_radius: f32,
+        filter: impl Fn(&SpatialEntry) -> bool,
+    ) -> Vec<(SpatialEntry, f32)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        // Grow the search until it holds k matches; everything within the
+        // radius was seen, so the k closest of those are the k closest.
+        let mut radius = self.cell_size.min(max_radius);
+        loop {
+            let mut found: Vec<(SpatialEntry, f32)> = self
+                .within_radius(center, radius)
+                .filter(|(entry, _)| filter(entry))
+                .map(|(entry, distance)| (*entry, distance))
+                .collect();
+            if found.len() >= k || radius >= max_radius {
+                found.sort_unstable_by(|a, b| a.1.total_cmp(&b.1));
+                found.truncate(k);
+                return found;
+            }
+            radius = (radius * 2.0).min(max_radius);
+        }
+    }
+
+    /// Every entry within `range` of `origin` and within `half_angle`
+    /// radians of `forward`, with its distance. Entries on top of
+    /// `origin` have no direction and are skipped.
+    pub fn in_cone(
+        &self,
+        origin: Vec3,
+        forward: Vec3,
+        half_angle: f32,
+        range: f32,
+    ) -> impl Iterator<Item = (&SpatialEntry, f32)> + '_ {
+        let forward = forward.normalize_or_zero();
+        self.within_radius(origin, range)
+            .filter(move |(entry, distance)| {
+                *distance >= 1e-3
+                    && forward.angle_between((entry.position - origin) / *distance) <= half_angle
+            })
+    }
+}
+
+fn rebuild_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    entities: Query<(Entity, &Transform, &Faction)>,
+) {
+    index.rebuild(entities.iter().map(|(entity, transform, faction)| SpatialEntry {
+        entity,
+        position: transform.translation,
+        faction: *faction,
+    }));
+    gauge!("spatial.entries").set(index.len() as f64);
+    gauge!("spatial.cells").set(index.cells.len() as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(positions: &[Vec3]) -> SpatialIndex {
+        let mut index = SpatialIndex::new(5.0);
+        index.rebuild(positions.iter().enumerate().map(|(i, &position)| SpatialEntry {
+            entity: Entity::from_raw_u32(i as u32).unwrap(),
+            position,
+            faction: Faction::Neutral,
+        }));
+        index
+    }
+
+    #[test]
+    fn radius_query_matches_brute_force() {
+        let positions: Vec<Vec3> = (0..200)
+            .map(|i| {
+                let i = i as f32;
+                Vec3::new((i * 7.3) % 50.0 - 25.0, (i * 3.1) % 30.0 - 15.0, (i * 11.7) % 40.0 - 20.0)
+            })
+            .collect();
+        let index = index(&positions);
+        // Small radius walks cells; a huge one walks occupied cells.
+        for radius in [3.0, 12.0, 500.0] {
+            let center = Vec3::new(2.0, -1.0, 4.0);
+            let mut found: Vec<u32> = index
+                .within_radius(center, radius)
+                .map(|(entry, _)| entry.entity.index_u32())
+                .collect();
+            found.sort_unstable();
+            let expected: Vec<u32> = (0..positions.len() as u32)
+                .filter(|&i| positions[i as usize].distance(center) <= radius)
+                .collect();
+            assert_eq!(found, expected, "radius {radius}");
+        }
+    }
+
+    #[test]
+    fn nearest_and_cone_queries() {
+        let index = index(&[
+            Vec3::new(0.0, 0.0, -3.0),
+            Vec3::new(0.0, 0.0, -30.0),
+            Vec3::new(20.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 8.0),
+        ]);
+        let nearest: Vec<u32> = index
+            .nearest(Vec3::ZERO, 2, 100.0, |_| true)
+            .iter()
+            .map(|(entry, _)| entry.entity.index_u32())
+            .collect();
+        assert_eq!(nearest, [0, 3]);
+        let ahead: Vec<u32> = index
+            .in_cone(Vec3::ZERO, Vec3::NEG_Z, 0.3, 50.0)
+            .map(|(entry, _)| entry.entity.index_u32())
+            .collect();
+        assert_eq!(ahead.len(), 2);
+        assert!(ahead.contains(&0) && ahead.contains(&1));
+    }
+}
@@ -0,0 +1,543 @@
+//! Squadrons: a leader and its wingmen flying in formation.
+//!
+//! A `Squadron` is its own entity listing its member ships, leader first;
+//! each member carries a `SquadronMember` pointing back at it. A wingman's
+//! slot is just its place in that list, laid out by the squadron's
+//! `Formation` in the leader's local frame. When a member dies,
+//! `reform_squadrons` drops it and everyone behind closes up a slot — and
+//! if it was the leader, the first wingman takes over.
+//!
+//! `pick_squadron_orders` decides what the squadron as a whole is doing,
+//! and the wingmen carry it out through their own utility AI: the
+//! "formation" scorer is 1 while they're ordered to form up, which the
+//! "wingman" archetype turns into `KeepFormation`, and an engage order
+//! hands every member the squadron's target in place of its own pick.
+//! Members also share what their sensors pick up: a contact any one of
+//! them has detected is a contact for all of them.
+
+use std::time::Instant;
+
+use bevy::color::palettes::css::*;
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use metrics::counter;
+
+use avian3d::prelude::LinearVelocity;
+
+use crate::ai::{
+    Action, AiLod, AiLodStats, AiSet, AiTarget, Contact, Perception, Scorer, Thinker, UtilityAiAppExt, select_targets,
+    update_perception,
+};
+use crate::combat::{ActingAgents, DebugAiViz, Evade, Mines, SteeringContext, gather_mines};
+use crate::steering::{self, Kinematics};
+
+pub struct SquadronPlugin;
+
+impl Plugin for SquadronPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SquadronSettings>()
+            .register_type::<Squadron>()
+            .register_type::<SquadronMember>()
+            .register_ai_scorer::<FormationScore>()
+            .register_ai_action::<KeepFormation>()
+            .add_systems(
+                PreUpdate,
+                share_squadron_contacts
+                    .in_set(AiSet::Sensing)
+                    .after(update_perception)
+                    .before(select_targets),
+            )
+            .add_systems(
+                PreUpdate,
+                (reform_squadrons, pick_squadron_orders, share_squadron_targets)
+                    .chain()
+                    .in_set(AiSet::Sensing)
+                    .after(select_targets),
+            )
+            .add_systems(PreUpdate, formation_scorer_system.in_set(AiSet::Scorers))
+            .add_systems(Update, keep_formation_action_system.in_set(AiSet::Actions))
+            .add_systems(Update, squadron_gizmos_system.run_if(resource_equals(DebugAiViz(true))));
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct SquadronSettings {
+    /// Seconds a squadron sticks with an order before reconsidering,
+    /// unless the target it was engaging is gone.
+    pub order_commit_time: f32,
+    /// Wingmen start easing off this far out from their slot.
+    pub slot_slowing_radius: f32,
+    /// Within this of its slot a wingman matches the leader's heading.
+    pub slot_tolerance: f32,
+}
+
+impl Default for SquadronSettings {
+    fn default() -> Self {
+        Self {
+            order_commit_time: 1.5,
+            slot_slowing_radius: 15.0,
+            slot_tolerance: 3.0,
+        }
+    }
+}
+
+/// Slot layouts. Offsets are in the leader's local frame: forward is -Z
+/// and right is +X, so wingmen trail at positive Z.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum Formation {
+    /// A diagonal line stepping back to the right.
+    #[default]
+    Echelon,
+    /// A V with the leader at the point.
+    Vic,
+    /// Side by side, alternating right and left of the leader.
+    LineAbreast,
+    /// Two pairs: wingman back left, element lead back right with its own
+    /// wingman further back right. Larger squadrons stack more of these
+    /// behind.
+    FingerFour,
+}
+
+impl Formation {
+    pub const ALL: [Formation; 4] = [
+        Formation::Echelon,
+        Formation::Vic,
+        Formation::LineAbreast,
+        Formation::FingerFour,
+    ];
+
+    /// Offset of `slot` from the leader, who is slot 0, with neighbouring
+    /// ships `spacing` apart.
+    pub fn slot_offset(self, slot: usize, spacing: f32) -> Vec3 {
+        if slot == 0 {
+            return Vec3::ZERO;
+        }
+        // Ranks out from the leader alternate right, left, right...
+        let rank = slot.div_ceil(2) as f32;
+        let side = if slot % 2 == 1 { 1.0 } else { -1.0 };
+        let offset = match self {
+            Formation::Echelon => Vec3::new(slot as f32, 0.0, slot as f32),
+            Formation::Vic => Vec3::new(side * rank, 0.0, rank),
+            Formation::LineAbreast => Vec3::new(side * rank, 0.0, 0.0),
+            Formation::FingerFour => {
+                let finger = match slot % 4 {
+                    0 => Vec3::ZERO,
+                    1 => Vec3::new(-1.0, 0.0, 1.0),
+                    2 => Vec3::new(1.0, 0.0, 1.0),
+                    _ => Vec3::new(2.0, 0.0, 2.0),
+                };
+                finger + Vec3::Z * (slot / 4) as f32 * 3.0
+            }
+        };
+        offset * spacing
+    }
+}
+
+/// What a squadron's wingmen have been told to do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum SquadronOrder {
+    /// Nothing to fight: hold formation on the leader.
+    #[default]
+    FormUp,
+    /// Everyone goes after the same target.
+    Engage(Entity),
+    /// The leader is running; every ship for itself.
+    Break,
+}
+
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Squadron {
+    pub formation: Formation,
+    /// Distance between neighbouring slots.
+    pub spacing: f32,
+    /// Leader first, then wingmen in slot order.
+    pub members: Vec<Entity>,
+    pub order: SquadronOrder,
+    /// Seconds since `order` was given.
+    order_held_for: f32,
+}
+
+impl Squadron {
+    pub fn new(formation: Formation, spacing: f32, members: Vec<Entity>) -> Self {
+        Self {
+            formation,
+            spacing,
+            members,
+            order: SquadronOrder::FormUp,
+            order_held_for: 0.0,
+        }
+    }
+
+    /// Spawns a squadron of `members`, the first of which leads.
+    pub fn spawn(commands: &mut Commands, formation: Formation, spacing: f32, members: Vec<Entity>) -> Entity {
+        let squadron = commands.spawn(Name::new("Squadron")).id();
+        for &member in &members {
+            commands.entity(member).insert(SquadronMember { squadron });
+        }
+        commands
+            .entity(squadron)
+            .insert(Squadron::new(formation, spacing, members));
+        squadron
+    }
+
+    pub fn leader(&self) -> Option<Entity> {
+        self.members.first().copied()
+    }
+
+    pub fn slot(&self, member: Entity) -> Option<usize> {
+        self.members.iter().position(|&entity| entity == member)
+    }
+
+    /// Drops members that no longer exist, closing up the slots behind
+    /// them. Returns whether anyone was dropped.
+    pub fn prune(&mut self, alive: impl Fn(Entity) -> bool) -> bool {
+        let before = self.members.len();
+        self.members.retain(|&member| alive(member));
+        self.members.len() != before
+    }
+}
+
+/// On a ship that belongs to a squadron.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+#[require(FormationScore)]
+pub struct SquadronMember {
+    pub squadron: Entity,
+}
+
+/// 1 for a wingman ordered to form up, otherwise 0.
+#[derive(Component, Default)]
+pub struct FormationScore(f32);
+
+impl Scorer for FormationScore {
+    const NAME: &'static str = "formation";
+
+    fn value(&self) -> f32 {
+        self.0
+    }
+}
+
+#[derive(Component, Default)]
+pub struct KeepFormation;
+
+impl Action for KeepFormation {
+    const NAME: &'static str = "formation";
+}
+
+/// Closes up squadrons after losses, promoting a new leader if needed,
+/// and despawns squadrons with nobody left.
+fn reform_squadrons(
+    mut commands: Commands,
+    mut squadrons: Query<(Entity, &mut Squadron)>,
+    members: Query<(), With<SquadronMember>>,
+) {
+    for (entity, mut squadron) in &mut squadrons {
+        if !squadron.prune(|member| members.contains(member)) {
+            continue;
+        }
+        if squadron.members.is_empty() {
+            commands.entity(entity).despawn();
+        } else {
+            counter!("ai.squadron_reforms").increment(1);
+        }
+    }
+}
+
+/// The squadron-level picker: engage whatever any member has in contact,
+/// preferring the leader's own target, break if the leader is evading, and
+/// otherwise form up.
+fn pick_squadron_orders(
+    time: Res<Time>,
+    settings: Res<SquadronSettings>,
+    mut squadrons: Query<&mut Squadron>,
+    agents: Query<(&AiTarget, Option<&Thinker>)>,
+) {
+    let dt = time.delta_secs();
+    for mut squadron in &mut squadrons {
+        squadron.order_held_for += dt;
+        let targets = || agents.iter_many(&squadron.members).map(|(target, _)| target);
+
+        let leader_evading = squadron
+            .leader()
+            .and_then(|leader| agents.get(leader).ok())
+            .is_some_and(|(_, thinker)| thinker.is_some_and(|thinker| thinker.current_action == Some(Evade::NAME)));
+        let contact = targets().find(|target| target.in_contact).and_then(|target| target.entity);
+        let order = if leader_evading {
+            SquadronOrder::Break
+        } else if let Some(contact) = contact {
+            SquadronOrder::Engage(contact)
+        } else {
+            SquadronOrder::FormUp
+        };
+
+        let target_gone = match squadron.order {
+            SquadronOrder::Engage(target) => !targets().any(|known| known.entity == Some(target)),
+            _ => false,
+        };
+        if order != squadron.order && (target_gone || squadron.order_held_for >= settings.order_commit_time) {
+            squadron.order = order;
+            squadron.order_held_for = 0.0;
+            counter!("ai.squadron_orders").increment(1);
+        }
+    }
+}
+
+/// Hands every member of an engaging squadron the squadron's target, as
+/// seen by whichever member is tracking it.
+fn share_squadron_targets(squadrons: Query<&Squadron>, mut agents: Query<&mut AiTarget>) {
+    for squadron in &squadrons {
+        let SquadronOrder::Engage(target) = squadron.order else {
+            continue;
+        };
+        // Prefer a member actually in contact over one remembering it.
+        let shared = agents
+            .iter_many(&squadron.members)
+            .filter(|known| known.entity == Some(target))
+            .max_by_key(|known| known.in_contact)
+            .and_then(|known| {
+                Some((known.last_known_position?, known.last_known_velocity, known.in_contact))
+            });
+        let Some((position, velocity, in_contact)) = shared else {
+            continue;
+        };
+        let mut members = agents.iter_many_mut(&squadron.members);
+        while let Some(mut known) = members.fetch_next() {
+            if known.entity != Some(target) || !known.in_contact {
+                known.assign(target, position, velocity, in_contact);
+            }
+        }
+    }
+}
+
+/// Pools the current contacts of every member that sensed this frame into
+/// every other member's `Perception`. Members that didn't think still
+/// hold last time's contacts as current, at stale positions, so they only
+/// receive.
+fn share_squadron_contacts(squadrons: Query<&Squadron>, mut agents: Query<(&mut Perception, Option<&AiLod>)>) {
+    for squadron in &squadrons {
+        let pooled: Vec<Contact> = agents
+            .iter_many(&squadron.members)
+            .filter(|(_, lod)| lod.is_none_or(|lod| lod.thinking()))
+            .flat_map(|(perception, _)| perception.current().copied().collect::<Vec<_>>())
+            .collect();
+        let mut members = agents.iter_many_mut(&squadron.members);
+        while let Some((mut perception, _)) = members.fetch_next() {
+            for contact in &pooled {
+                perception.reveal(contact.entity, contact.position, contact.velocity);
+            }
+        }
+    }
+}
+
+fn formation_scorer_system(
+    squadrons: Query<&Squadron>,
+    mut agents: Query<(Entity, &SquadronMember, Option<&AiLod>, &mut FormationScore)>,
+    mut stats: ResMut<AiLodStats>,
+) {
+    for (entity, member, lod, mut score) in &mut agents {
+        if lod.is_some_and(|lod| !lod.thinking()) {
+            continue;
+        }
+        let started = Instant::now();
+        score.0 = match squadrons.get(member.squadron) {
+            Ok(squadron) if squadron.order == SquadronOrder::FormUp && squadron.leader() != Some(entity) => 1.0,
+            _ => 0.0,
+        };
+        if let Some(lod) = lod {
+            stats.record(lod.tier, started);
+        }
+    }
+}
+
+/// Where a wingman's slot is right now and how it's moving.
+struct Slot {
+    position: Vec3,
+    velocity: Vec3,
+    forward: Vec3,
+}
+
+type Leaders<'w, 's> = Query<'w, 's, (&'static Transform, &'static LinearVelocity)>;
+
+/// Flies each wingman to its slot and holds it there, matching the
+/// leader's velocity and, once settled, its heading.
+fn keep_formation_action_system(
+    mut agents: ParamSet<(ActingAgents<KeepFormation>, Mines, Leaders)>,
+    squadrons: Query<&Squadron>,
+    settings: Res<SquadronSettings>,
+    steering: SteeringContext,
+) {
+    let mines = gather_mines(&agents.p1());
+    let mut slots = HashMap::new();
+    {
+        let leaders = agents.p2();
+        for squadron in &squadrons {
+            let Some((leader, leader_velocity)) = squadron.leader().and_then(|leader| leaders.get(leader).ok()) else {
+                continue;
+            };
+            for (slot, &member) in squadron.members.iter().enumerate().skip(1) {
+                let offset = squadron.formation.slot_offset(slot, squadron.spacing);
+                slots.insert(
+                    member,
+                    Slot {
+                        position: leader.translation + leader.rotation * offset,
+                        velocity: leader_velocity.0,
+                        forward: *leader.forward(),
+                    },
+                );
+            }
+        }
+    }
+
+    let dt = steering.time.delta_secs();
+    for (entity, faction, _, transform, mut linvel, mut heading, subsystems, limits, _) in &mut agents.p0() {
+        let Some(slot) = slots.get(&entity) else {
+            continue;
+        };
+        let limits = steering.limits(limits, subsystems);
+        let agent = Kinematics {
+            position: transform.translation,
+            velocity: linvel.0,
+        };
+        // Arrive in the leader's frame of reference, so the wingman comes
+        // to rest relative to its slot rather than to the world.
+        let relative = Kinematics {
+            velocity: agent.velocity - slot.velocity,
+            ..agent
+        };
+        let keep = steering::arrive(&relative, slot.position, settings.slot_slowing_radius, limits);
+        let acceleration = steering
+            .hazards(entity, *faction, &agent, &mines, limits)
+            .add(keep, 1.0)
+            .finish(limits);
+        linvel.0 = steering::integrate(linvel.0, acceleration, dt, limits);
+
+        heading.0 = if agent.position.distance(slot.position) <= settings.slot_tolerance {
+            Some(slot.forward)
+        } else {
+            linvel.0.try_normalize()
+        };
+    }
+}
+
+/// Links each leader to its wingmen, colored by the squadron's order.
+fn squadron_gizmos_system(
+    mut gizmos: Gizmos,
+    squadrons: Query<&Squadron>,
+    ships: Query<&Transform>,
+) {
+    for squadron in &squadrons {
+        let color = match squadron.order {
+            SquadronOrder::FormUp => Color::from(LIGHT_SKY_BLUE),
+            SquadronOrder::Engage(_) => Color::from(ORANGE),
+            SquadronOrder::Break => Color::from(RED).with_alpha(0.4),
+        };
+        let Some(leader) = squadron.leader().and_then(|leader| ships.get(leader).ok()) else {
+            continue;
+        };
+        for wingman in ships.iter_many(squadron.members.iter().skip(1)) {
+            gizmos.line(leader.translation, wingman.translation, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use crate::ai::TargetingSettings;
+    use crate::combat::AiMarker;
+    use crate::faction::Faction;
+
+    use super::*;
+
+    #[test]
+    fn slots_trail_the_leader_symmetrically() {
+        assert_eq!(Formation::Vic.slot_offset(0, 10.0), Vec3::ZERO);
+        let right = Formation::Vic.slot_offset(1, 10.0);
+        let left = Formation::Vic.slot_offset(2, 10.0);
+        assert_eq!(right, Vec3::new(10.0, 0.0, 10.0));
+        assert_eq!(left, Vec3::new(-10.0, 0.0, 10.0));
+        assert_eq!(Formation::LineAbreast.slot_offset(3, 10.0), Vec3::new(20.0, 0.0, 0.0));
+        // Every slot in every formation is distinct.
+        for formation in Formation::ALL {
+            let offsets: Vec<Vec3> = (0..8).map(|slot| formation.slot_offset(slot, 1.0)).collect();
+            for (i, a) in offsets.iter().enumerate() {
+                assert!(offsets[i + 1..].iter().all(|b| a.distance(*b) > 0.5), "{formation:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn pruning_promotes_the_first_wingman() {
+        let ships: Vec<Entity> = (0..4).map(|i| Entity::from_raw_u32(i).unwrap()).collect();
+        let mut squadron = Squadron::new(Formation::FingerFour, 10.0, ships.clone());
+        assert!(!squadron.prune(|_| true));
+        assert!(squadron.prune(|ship| ship != ships[0]));
+        assert_eq!(squadron.leader(), Some(ships[1]));
+        assert_eq!(squadron.slot(ships[3]), Some(2));
+    }
+
+    #[test]
+    fn engaged_wingmen_keep_the_squadron_target() {
+        let mut world = crate::combat::test_world();
+        world.init_resource::<TargetingSettings>();
+        world.init_resource::<AiLodStats>();
+        let far = world
+            .spawn((Transform::from_xyz(0.0, 0.0, -300.0), Faction::Raiders))
+            .id();
+        let near = world
+            .spawn((Transform::from_xyz(20.0, 0.0, -20.0), Faction::Raiders))
+            .id();
+        // The leader is tracking the squadron's target; the wingman's own
+        // sensors only have something else.
+        let mut leader_sees = Perception::default();
+        leader_sees.reveal(far, Vec3::new(0.0, 0.0, -300.0), Vec3::ZERO);
+        let mut wingman_sees = Perception::default();
+        wingman_sees.reveal(near, Vec3::new(20.0, 0.0, -20.0), Vec3::ZERO);
+        let leader = world
+            .spawn((AiMarker, Transform::default(), Faction::Player, AiTarget::default(), leader_sees))
+            .id();
+        let wingman = world
+            .spawn((AiMarker, Transform::default(), Faction::Player, AiTarget::default(), wingman_sees))
+            .id();
+        let mut squadron = Squadron::new(Formation::Vic, 10.0, vec![leader, wingman]);
+        squadron.order = SquadronOrder::Engage(far);
+        world.spawn(squadron);
+
+        for frame in 0..10 {
+            world.run_system_once(select_targets).unwrap();
+            if frame > 0 {
+                assert_eq!(world.get::<AiTarget>(wingman).unwrap().entity, Some(far), "frame {frame}");
+            }
+            world.run_system_once(share_squadron_targets).unwrap();
+        }
+        assert_eq!(world.get::<AiTarget>(leader).unwrap().entity, Some(far));
+    }
+
+    #[test]
+    fn only_members_that_sensed_share_contacts() {
+        let mut world = World::new();
+        let hostile = world.spawn_empty().id();
+        let ghost = world.spawn_empty().id();
+        let live = Vec3::new(0.0, 0.0, -40.0);
+        let mut sensed = Perception::default();
+        sensed.reveal(hostile, live, Vec3::ZERO);
+        // Skipped thinking this frame: still holding last think's contacts
+        // as current, one of them long gone.
+        let mut stale = Perception::default();
+        stale.reveal(hostile, Vec3::new(0.0, 0.0, -90.0), Vec3::ZERO);
+        stale.reveal(ghost, Vec3::new(50.0, 0.0, 0.0), Vec3::ZERO);
+        let thinking = world.spawn(sensed).id();
+        let idle = world.spawn((stale, AiLod::default())).id();
+        world.spawn(Squadron::new(Formation::Vic, 10.0, vec![thinking, idle]));
+
+        world.run_system_once(share_squadron_contacts).unwrap();
+        let sensed = world.get::<Perception>(thinking).unwrap();
+        assert_eq!(sensed.contacts().len(), 1);
+        assert_eq!(sensed.contacts()[0].position, live);
+        let stale = world.get::<Perception>(idle).unwrap();
+        let shared = stale.contacts().iter().find(|contact| contact.entity == hostile).unwrap();
+        assert_eq!(shared.position, live);
+    }
+}
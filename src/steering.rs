@@ -0,0 +1,357 @@
+//! Steering behaviors for AI ships.
+//!
+//! Every behavior is a pure function that returns the acceleration the
+//! ship would like to apply — typically "desired velocity minus current
+//! velocity" — without applying it. Callers weigh several together with
+//! `SteeringBlend`, which clamps the sum to the ship's `SteeringLimits`,
+//! and then `integrate` the result into a velocity capped at top speed.
+//! Facing is steered separately: the pilot sets a `DesiredHeading` and
+//! `turn_towards` gives the angular velocity that swings the nose round
+//! to it within the ship's turn rate, banking into the turn on the way.
+//! Nothing here touches the ECS beyond the `SteeringLimits`,
+//! `DesiredHeading` and `Wander` components the AI keeps on each ship.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+/// How hard a ship can push itself around.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct SteeringLimits {
+    pub max_speed: f32,
+    pub max_acceleration: f32,
+    /// Radians per second.
+    pub max_turn_rate: f32,
+    /// Steepest roll, in radians, the ship banks to in a hard turn.
+    pub max_bank: f32,
+}
+
+impl Default for SteeringLimits {
+    fn default() -> Self {
+        Self {
+            max_speed: 18.0,
+            max_acceleration: 30.0,
+            max_turn_rate: 2.0,
+            max_bank: 0.8,
+        }
+    }
+}
+
+impl SteeringLimits {
+    /// These limits with speed, acceleration and turn rate scaled, e.g. by
+    /// engine efficiency.
+    pub fn scaled(self, factor: f32) -> Self {
+        Self {
+            max_speed: self.max_speed * factor,
+            max_acceleration: self.max_acceleration * factor,
+            max_turn_rate: self.max_turn_rate * factor,
+            ..self
+        }
+    }
+}
+
+/// Position and velocity of the ship doing the steering.
+#[derive(Clone, Copy, Debug)]
+pub struct Kinematics {
+    pub position: Vec3,
+    pub velocity: Vec3,
+}
+
+/// Accumulates weighted behaviors.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SteeringBlend {
+    total: Vec3,
+}
+
+impl SteeringBlend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(mut self, acceleration: Vec3, weight: f32) -> Self {
+        self.total += acceleration * weight;
+        self
+    }
+
+    /// The weighted sum, clamped to the ship's acceleration.
+    pub fn finish(self, limits: SteeringLimits) -> Vec3 {
+        self.total.clamp_length_max(limits.max_acceleration)
+    }
+}
+
+/// Velocity after accelerating at `acceleration` for `dt`, capped at top
+/// speed.
+pub fn integrate(velocity: Vec3, acceleration: Vec3, dt: f32, limits: SteeringLimits) -> Vec3 {
+    (velocity + acceleration * dt).clamp_length_max(limits.max_speed)
+}
+
+/// Acceleration that turns the current velocity into `desired`.
+fn steer_towards(agent: &Kinematics, desired: Vec3) -> Vec3 {
+    desired - agent.velocity
+}
+
+/// Full speed straight at `target`.
+pub fn seek(agent: &Kinematics, target: Vec3, limits: SteeringLimits) -> Vec3 {
+    let desired = (target - agent.position).normalize_or_zero() * limits.max_speed;
+    steer_towards(agent, desired)
+}
+
+/// Full speed straight away from `threat`.
+pub fn flee(agent: &Kinematics, threat: Vec3, limits: SteeringLimits) -> Vec3 {
+    let desired = (agent.position - threat).normalize_or_zero() * limits.max_speed;
+    steer_towards(agent, desired)
+}
+
+/// Like `seek`, but slowing linearly inside `slowing_radius` so the ship
+/// comes to rest on `target` instead of overshooting it.
+pub fn arrive(agent: &Kinematics, target: Vec3, slowing_radius: f32, limits: SteeringLimits) -> Vec3 {
+    let offset = target - agent.position;
+    let distance = offset.length();
+    let speed = limits.max_speed * (distance / slowing_radius.max(1e-3)).min(1.0);
+    steer_towards(agent, offset.normalize_or_zero() * speed)
+}
+
+/// Where a target moving at `target_velocity` will be by the time the
+/// agent could get there, looking no more than `max_prediction` seconds
+/// ahead.
+fn predict(agent: &Kinematics, target: Vec3, target_velocity: Vec3, limits: SteeringLimits, max_prediction: f32) -> Vec3 {
+    let distance = agent.position.distance(target);
+    let speed = agent.velocity.length().max(limits.max_speed);
+    let lookahead = (distance / speed.max(1e-3)).min(max_prediction);
+    target + target_velocity * lookahead
+}
+
+/// Seek towards where a moving target is going to be.
+pub fn pursue(
+    agent: &Kinematics,
+    target: Vec3,
+    target_velocity: Vec3,
+    limits: SteeringLimits,
+    max_prediction: f32,
+) -> Vec3 {
+    seek(agent, predict(agent, target, target_velocity, limits, max_prediction), limits)
+}
+
+/// Flee from where a moving threat is going to be.
+pub fn evade(
+    agent: &Kinematics,
+    threat: Vec3,
+    threat_velocity: Vec3,
+    limits: SteeringLimits,
+    max_prediction: f32,
+) -> Vec3 {
+    flee(agent, predict(agent, threat, threat_velocity, limits, max_prediction), limits)
+}
+
+/// Where the pilot wants the ship's nose pointed. `None` holds the
+/// current attitude.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct DesiredHeading(pub Option<Vec3>);
+
+/// How quickly, per second, the ship closes the gap to its desired
+/// attitude before the turn rate caps it. Kept well under the physics
+/// tick rate so the turn settles instead of oscillating.
+const TURN_RESPONSE: f32 = 4.0;
+
+/// Angular velocity that turns a ship at `rotation` towards `heading`,
+/// capped at its turn rate. The ship rolls into the turn, banking up to
+/// `max_bank` the further off to one side the heading is, and levels out
+/// against world up once it's on course.
+pub fn turn_towards(rotation: Quat, heading: Vec3, limits: SteeringLimits) -> Vec3 {
+    let Some(heading) = heading.try_normalize() else {
+        return Vec3::ZERO;
+    };
+    let side = (rotation * Vec3::X).dot(heading);
+    // Rolling about the nose, positive drops the right wing.
+    let bank = Quat::from_axis_angle(heading, limits.max_bank * side.clamp(-1.0, 1.0));
+    let desired = Transform::default().looking_to(heading, bank * Vec3::Y).rotation;
+    let (axis, angle) = (desired * rotation.inverse()).to_axis_angle();
+    // Take the short way round.
+    let angle = if angle > std::f32::consts::PI {
+        angle - std::f32::consts::TAU
+    } else {
+        angle
+    };
+    (axis * angle * TURN_RESPONSE).clamp_length_max(limits.max_turn_rate)
+}
+
+/// State for `wander`: a point that drifts randomly around a sphere held
+/// out in front of the ship.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Wander {
+    /// Current point on the unit sphere.
+    pub direction: Vec3,
+    /// How far ahead the sphere sits.
+    pub distance: f32,
+    pub radius: f32,
+    /// How far the point may move per second.
+    pub jitter: f32,
+}
+
+impl Default for Wander {
+    fn default() -> Self {
+        Self {
+            direction: Vec3::NEG_Z,
+            distance: 12.0,
+            radius: 6.0,
+            jitter: 2.0,
+        }
+    }
+}
+
+/// Aimless but smooth cruising: seek a point on the wander sphere ahead
+/// of the ship, nudging that point randomly each call.
+pub fn wander(
+    agent: &Kinematics,
+    forward: Vec3,
+    state: &mut Wander,
+    dt: f32,
+    rng: &mut impl Rng,
+    limits: SteeringLimits,
+) -> Vec3 {
+    let nudge = Vec3::new(
+        rng.gen_range(-1.0..1.0),
+        rng.gen_range(-1.0..1.0),
+        rng.gen_range(-1.0..1.0),
+    ) * state.jitter
+        * dt;
+    state.direction = (state.direction + nudge).try_normalize().unwrap_or(forward);
+    let heading = agent.velocity.try_normalize().unwrap_or(forward);
+    let target = agent.position + heading * state.distance + state.direction * state.radius;
+    seek(agent, target, limits)
+}
+
+/// Push away from neighbours closer than `radius`, stronger the closer
+/// they are.
+pub fn separation(
+    agent: &Kinematics,
+    neighbours: impl IntoIterator<Item = Vec3>,
+    radius: f32,
+    limits: SteeringLimits,
+) -> Vec3 {
+    let push: Vec3 = neighbours
+        .into_iter()
+        .filter_map(|neighbour| {
+            let offset = agent.position - neighbour;
+            let distance = offset.length();
+            (distance > 1e-3 && distance < radius)
+                .then(|| offset / distance * (1.0 - distance / radius))
+        })
+        .sum();
+    push.clamp_length_max(1.0) * limits.max_acceleration
+}
+
+/// Seek the centre of a group of neighbours.
+pub fn cohesion(
+    agent: &Kinematics,
+    neighbours: impl IntoIterator<Item = Vec3>,
+    limits: SteeringLimits,
+) -> Vec3 {
+    let (sum, count) = neighbours
+        .into_iter()
+        .fold((Vec3::ZERO, 0), |(sum, count), neighbour| (sum + neighbour, count + 1));
+    if count == 0 {
+        return Vec3::ZERO;
+    }
+    seek(agent, sum / count as f32, limits)
+}
+
+/// Steer off an obstacle found `distance` ahead along the velocity, with
+/// surface `normal` at the hit point, harder the closer it is. Nothing
+/// within `lookahead` means no correction.
+pub fn avoid_obstacle(
+    agent: &Kinematics,
+    hit: Option<(f32, Vec3)>,
+    lookahead: f32,
+    limits: SteeringLimits,
+) -> Vec3 {
+    let Some((distance, normal)) = hit else {
+        return Vec3::ZERO;
+    };
+    if distance >= lookahead {
+        return Vec3::ZERO;
+    }
+    let urgency = 1.0 - distance / lookahead.max(1e-3);
+    // Deflect sideways along the surface rather than straight back, so the
+    // ship slides past instead of stopping dead.
+    let heading = agent.velocity.normalize_or_zero();
+    let sideways = (normal - heading * normal.dot(heading)).try_normalize().unwrap_or(normal);
+    (sideways + normal * 0.5).normalize_or_zero() * limits.max_acceleration * urgency
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: SteeringLimits = SteeringLimits {
+        max_speed: 10.0,
+        max_acceleration: 20.0,
+        max_turn_rate: 2.0,
+        max_bank: 0.8,
+    };
+
+    fn at_rest(position: Vec3) -> Kinematics {
+        Kinematics {
+            position,
+            velocity: Vec3::ZERO,
+        }
+    }
+
+    #[test]
+    fn arrive_slows_inside_radius_and_stops_on_target() {
+        let far = arrive(&at_rest(Vec3::ZERO), Vec3::NEG_Z * 100.0, 20.0, LIMITS);
+        let near = arrive(&at_rest(Vec3::ZERO), Vec3::NEG_Z * 5.0, 20.0, LIMITS);
+        assert!((far.length() - LIMITS.max_speed).abs() < 1e-4);
+        assert!(near.length() < far.length());
+        let moving_on_target = Kinematics {
+            position: Vec3::ZERO,
+            velocity: Vec3::X * 4.0,
+        };
+        // Sitting on the target: brake.
+        assert!(arrive(&moving_on_target, Vec3::ZERO, 20.0, LIMITS).x < 0.0);
+    }
+
+    #[test]
+    fn pursue_leads_and_evade_runs_from_prediction() {
+        let agent = at_rest(Vec3::ZERO);
+        let target = Vec3::NEG_Z * 20.0;
+        let crossing = Vec3::X * 10.0;
+        assert!(pursue(&agent, target, crossing, LIMITS, 5.0).x > 0.0);
+        let away = evade(&agent, target, crossing, LIMITS, 5.0);
+        assert!(away.z > 0.0 && away.x < 0.0);
+    }
+
+    #[test]
+    fn blend_is_clamped_and_integration_caps_speed() {
+        let blended = SteeringBlend::new()
+            .add(Vec3::X * 50.0, 1.0)
+            .add(Vec3::Y * 50.0, 0.5)
+            .finish(LIMITS);
+        assert!((blended.length() - LIMITS.max_acceleration).abs() < 1e-4);
+        let velocity = integrate(Vec3::X * 9.0, Vec3::X * 100.0, 1.0, LIMITS);
+        assert!((velocity.length() - LIMITS.max_speed).abs() < 1e-4);
+    }
+
+    #[test]
+    fn turning_is_rate_limited_and_banks_into_the_turn() {
+        // Target dead astern to the right: full rate, not an instant snap.
+        let behind = turn_towards(Quat::IDENTITY, Vec3::new(0.3, 0.0, 1.0), LIMITS);
+        assert!((behind.length() - LIMITS.max_turn_rate).abs() < 1e-4);
+        // Off to the right: yaw right (negative about Y) and roll right,
+        // which about the -Z nose is a positive rotation.
+        let right = turn_towards(Quat::IDENTITY, Vec3::new(1.0, 0.0, -2.0), LIMITS);
+        assert!(right.y < 0.0);
+        assert!(right.z < 0.0);
+        // On course and level: nothing to do.
+        assert!(turn_towards(Quat::IDENTITY, Vec3::NEG_Z, LIMITS).length() < 1e-4);
+    }
+
+    #[test]
+    fn separation_ignores_distant_neighbours() {
+        let agent = at_rest(Vec3::ZERO);
+        assert_eq!(separation(&agent, [Vec3::X * 10.0], 5.0, LIMITS), Vec3::ZERO);
+        assert!(separation(&agent, [Vec3::X * 2.0], 5.0, LIMITS).x < 0.0);
+    }
+}
@@ -0,0 +1,173 @@
+//! Damageable ship subsystems: engines, weapon mounts, sensors and the
+//! shield generator.
+//!
+//! Part of every hull hit spills into one subsystem — the one the shooter
+//! was aiming for if they picked one, otherwise a random one. A damaged
+//! subsystem degrades whatever it drives by its `efficiency`: engines cap
+//! max speed, weapon mounts stretch cooldowns, sensors shorten missile lock
+//! range, and the shield generator slows regeneration. Damage control
+//! slowly repairs the worst-hit subsystem over time.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::common::Player;
+
+pub struct SubsystemsPlugin;
+
+impl Plugin for SubsystemsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (repair_subsystems, cycle_subsystem_target));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Reflect)]
+pub enum SubsystemKind {
+    Engines,
+    Weapons,
+    Sensors,
+    ShieldGenerator,
+}
+
+impl SubsystemKind {
+    pub const ALL: [SubsystemKind; 4] = [
+        SubsystemKind::Engines,
+        SubsystemKind::Weapons,
+        SubsystemKind::Sensors,
+        SubsystemKind::ShieldGenerator,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SubsystemKind::Engines => "ENGINES",
+            SubsystemKind::Weapons => "WEAPONS",
+            SubsystemKind::Sensors => "SENSORS",
+            SubsystemKind::ShieldGenerator => "SHIELDS",
+        }
+    }
+
+    pub fn random() -> Self {
+        Self::ALL[rand::thread_rng().gen_range(0..Self::ALL.len())]
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Subsystems {
+    /// Current health per subsystem, indexed by `SubsystemKind as usize`.
+    pub health: [f32; 4],
+    pub max_health: f32,
+    /// Efficiency of a fully destroyed subsystem, so a crippled ship limps
+    /// rather than going dead in space.
+    pub min_efficiency: f32,
+    /// Fraction of hull damage that also lands on a subsystem.
+    pub damage_share: f32,
+    /// Damage-control repair, in health per second, spent on whichever
+    /// subsystem is worst off.
+    pub repair_rate: f32,
+}
+
+impl Subsystems {
+    pub fn new(max_health: f32) -> Self {
+        Self {
+            health: [max_health; 4],
+            max_health,
+            min_efficiency: 0.25,
+            damage_share: 0.5,
+            repair_rate: 2.0,
+        }
+    }
+
+    pub fn health(&self, kind: SubsystemKind) -> f32 {
+        self.health[kind as usize]
+    }
+
+    /// Scales whatever `kind` drives: 1 when intact, `min_efficiency` when
+    /// destroyed, linear in between.
+    pub fn efficiency(&self, kind: SubsystemKind) -> f32 {
+        let fraction = (self.health(kind) / self.max_health.max(1e-3)).clamp(0.0, 1.0);
+        self.min_efficiency + (1.0 - self.min_efficiency) * fraction
+    }
+
+    /// Lands the subsystem share of `hull_damage` on `kind`.
+    pub fn take_hull_damage(&mut self, kind: SubsystemKind, hull_damage: f32) {
+        let health = &mut self.health[kind as usize];
+        *health = (*health - hull_damage * self.damage_share).max(0.0);
+    }
+
+    pub fn repair(&mut self, kind: SubsystemKind, amount: f32) {
+        let health = &mut self.health[kind as usize];
+        *health = (*health + amount).min(self.max_health);
+    }
+
+    pub fn most_damaged(&self) -> Option<SubsystemKind> {
+        SubsystemKind::ALL
+            .into_iter()
+            .filter(|kind| self.health(*kind) < self.max_health)
+            .min_by(|a, b| self.health(*a).total_cmp(&self.health(*b)))
+    }
+}
+
+/// Efficiency of `kind` on a ship that may not model subsystems at all.
+pub fn subsystem_efficiency(subsystems: Option<&Subsystems>, kind: SubsystemKind) -> f32 {
+    subsystems.map_or(1.0, |subsystems| subsystems.efficiency(kind))
+}
+
+/// Which enemy subsystem a ship's shots are aimed at; copied onto each
+/// projectile it fires. `None` spreads damage at random.
+#[derive(Component, Default, Debug)]
+pub struct SubsystemTarget(pub Option<SubsystemKind>);
+
+fn repair_subsystems(time: Res<Time>, mut query: Query<&mut Subsystems>) {
+    let dt = time.delta_secs();
+    for mut subsystems in &mut query {
+        if let Some(kind) = subsystems.most_damaged() {
+            let amount = subsystems.repair_rate * dt;
+            subsystems.repair(kind, amount);
+        }
+    }
+}
+
+/// T cycles the player's subsystem target: none → engines → … → none.
+fn cycle_subsystem_target(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut player: Query<&mut SubsystemTarget, With<Player>>,
+) {
+    if !keys.just_pressed(KeyCode::KeyT) {
+        return;
+    }
+    let Ok(mut target) = player.single_mut() else {
+        return;
+    };
+    target.0 = match target.0 {
+        None => Some(SubsystemKind::ALL[0]),
+        Some(kind) => SubsystemKind::ALL.get(kind as usize + 1).copied(),
+    };
+    info!("Subsystem target: {:?}", target.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn efficiency_bottoms_out_at_floor() {
+        let mut subsystems = Subsystems::new(50.0);
+        assert_eq!(subsystems.efficiency(SubsystemKind::Engines), 1.0);
+        subsystems.take_hull_damage(SubsystemKind::Engines, 1000.0);
+        assert_eq!(subsystems.health(SubsystemKind::Engines), 0.0);
+        assert_eq!(
+            subsystems.efficiency(SubsystemKind::Engines),
+            subsystems.min_efficiency
+        );
+    }
+
+    #[test]
+    fn repair_targets_worst_subsystem() {
+        let mut subsystems = Subsystems::new(50.0);
+        subsystems.take_hull_damage(SubsystemKind::Sensors, 40.0);
+        subsystems.take_hull_damage(SubsystemKind::Weapons, 10.0);
+        assert_eq!(subsystems.most_damaged(), Some(SubsystemKind::Sensors));
+        subsystems.repair(SubsystemKind::Sensors, 100.0);
+        assert_eq!(subsystems.health(SubsystemKind::Sensors), 50.0);
+    }
+}
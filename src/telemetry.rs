@@ -0,0 +1,611 @@
+//! In-game metrics.
+//!
+//! The AI, weapons and spatial index report through the `metrics` facade
+//! (`gauge!`, `counter!`, `histogram!`). `MetricsPlugin` installs the
+//! recorder those land in, a `metrics_util` registry of atomics, and every
+//! `refresh_interval` copies it into `MetricsSnapshot`. Histogram samples
+//! are drained into a running `Summary` per series at each refresh, so
+//! they're reported as quantiles.
+//!
+//! A series about one entity carries an `ENTITY_LABEL` made by
+//! `entity_label`. The panel groups those by entity, and they're dropped
+//! once the entity is despawned rather than piling up.
+//!
+//! A `DumpMetrics` message, or a button in the panel, writes the latest
+//! snapshot to `dump_dir` as CSV, JSON or Prometheus text.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+use metrics_util::registry::{AtomicStorage, Registry};
+use metrics_util::storage::Summary;
+
+pub struct MetricsPlugin;
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MetricsSettings>()
+            .init_resource::<MetricsSnapshot>()
+            .insert_resource(MetricsStore {
+                registry: global_registry(),
+                summaries: HashMap::default(),
+                since_refresh: f32::INFINITY,
+            })
+            .add_message::<DumpMetrics>()
+            .add_systems(Last, (refresh_metrics, dump_metrics).chain())
+            .add_systems(EguiPrimaryContextPass, metrics_panel);
+    }
+}
+
+/// Label key for series about a single entity; see `entity_label`.
+pub const ENTITY_LABEL: &str = "entity";
+
+/// The `ENTITY_LABEL` value for `entity`.
+pub fn entity_label(entity: Entity) -> String {
+    entity.to_bits().to_string()
+}
+
+fn label_entity(value: &str) -> Option<Entity> {
+    value.parse().ok().and_then(Entity::try_from_bits)
+}
+
+type MetricsRegistry = Registry<Key, AtomicStorage>;
+
+struct GameRecorder(Arc<MetricsRegistry>);
+
+impl Recorder for GameRecorder {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        self.0.get_or_create_counter(key, |counter| Counter::from_arc(counter.clone()))
+    }
+
+    fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+        self.0.get_or_create_gauge(key, |gauge| Gauge::from_arc(gauge.clone()))
+    }
+
+    fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+        self.0.get_or_create_histogram(key, |histogram| Histogram::from_arc(histogram.clone()))
+    }
+}
+
+/// The registry the global recorder writes to. The recorder can only be
+/// installed once per process, so every app shares it.
+fn global_registry() -> Arc<MetricsRegistry> {
+    static REGISTRY: OnceLock<Arc<MetricsRegistry>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| {
+            let registry = Arc::new(Registry::atomic());
+            if metrics::set_global_recorder(GameRecorder(registry.clone())).is_err() {
+                warn!("Another metrics recorder is already installed; the metrics panel will stay empty");
+            }
+            registry
+        })
+        .clone()
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct MetricsSettings {
+    /// Seconds between snapshots.
+    pub refresh_interval: f32,
+    /// Where `DumpMetrics` writes to.
+    pub dump_dir: PathBuf,
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            refresh_interval: 0.25,
+            dump_dir: PathBuf::from("metrics"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HistogramStats {
+    pub count: usize,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+impl HistogramStats {
+    fn of(summary: &Summary) -> Self {
+        let quantile = |q| summary.quantile(q).unwrap_or(0.0);
+        Self {
+            count: summary.count(),
+            sum: summary.sum().unwrap_or(0.0),
+            min: summary.min(),
+            max: summary.max(),
+            p50: quantile(0.5),
+            p90: quantile(0.9),
+            p99: quantile(0.99),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetricValue {
+    Counter(u64),
+    Gauge(f64),
+    Histogram(HistogramStats),
+}
+
+impl MetricValue {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MetricValue::Counter(_) => "counter",
+            MetricValue::Gauge(_) => "gauge",
+            MetricValue::Histogram(_) => "histogram",
+        }
+    }
+}
+
+impl std::fmt::Display for MetricValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetricValue::Counter(value) => write!(f, "{value}"),
+            MetricValue::Gauge(value) => write!(f, "{value:.3}"),
+            MetricValue::Histogram(stats) => {
+                write!(f, "n={} p50={:.3} p90={:.3} p99={:.3}", stats.count, stats.p50, stats.p90, stats.p99)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricSample {
+    pub name: String,
+    pub labels: Vec<(String, String)>,
+    pub value: MetricValue,
+}
+
+impl MetricSample {
+    fn new(key: &Key, value: MetricValue) -> Self {
+        Self {
+            name: key.name().to_string(),
+            labels: key
+                .labels()
+                .map(|label| (label.key().to_string(), label.value().to_string()))
+                .collect(),
+            value,
+        }
+    }
+
+    /// The entity this series is about, if any.
+    pub fn entity(&self) -> Option<Entity> {
+        self.labels
+            .iter()
+            .find(|(key, _)| key == ENTITY_LABEL)
+            .and_then(|(_, value)| label_entity(value))
+    }
+}
+
+/// Every series as of the last refresh, sorted by name then labels.
+#[derive(Resource, Default, Debug)]
+pub struct MetricsSnapshot {
+    /// Seconds since startup the snapshot was taken at.
+    pub time: f64,
+    pub samples: Vec<MetricSample>,
+}
+
+#[derive(Resource)]
+struct MetricsStore {
+    registry: Arc<MetricsRegistry>,
+    summaries: HashMap<Key, Summary>,
+    since_refresh: f32,
+}
+
+fn refresh_metrics(
+    time: Res<Time>,
+    settings: Res<MetricsSettings>,
+    mut store: ResMut<MetricsStore>,
+    mut snapshot: ResMut<MetricsSnapshot>,
+    entities: Query<()>,
+) {
+    let MetricsStore {
+        registry,
+        summaries,
+        since_refresh,
+    } = &mut *store;
+    *since_refresh += time.delta_secs();
+    if *since_refresh < settings.refresh_interval {
+        return;
+    }
+    *since_refresh = 0.0;
+
+    // Series about despawned entities will never change again.
+    let live = |key: &Key| {
+        key.labels()
+            .find(|label| label.key() == ENTITY_LABEL)
+            .and_then(|label| label_entity(label.value()))
+            .is_none_or(|entity| entities.contains(entity))
+    };
+    registry.retain_counters(|key, _| live(key));
+    registry.retain_gauges(|key, _| live(key));
+    registry.retain_histograms(|key, _| live(key));
+    summaries.retain(|key, _| live(key));
+
+    let mut samples = Vec::new();
+    registry.visit_counters(|key, counter| {
+        samples.push(MetricSample::new(key, MetricValue::Counter(counter.load(Ordering::Relaxed))));
+    });
+    registry.visit_gauges(|key, gauge| {
+        let value = f64::from_bits(gauge.load(Ordering::Relaxed));
+        samples.push(MetricSample::new(key, MetricValue::Gauge(value)));
+    });
+    registry.visit_histograms(|key, bucket| {
+        let summary = summaries.entry(key.clone()).or_insert_with(Summary::with_defaults);
+        bucket.clear_with(|values| {
+            for value in values {
+                summary.add(*value);
+            }
+        });
+    });
+    for (key, summary) in summaries.iter() {
+        samples.push(MetricSample::new(key, MetricValue::Histogram(HistogramStats::of(summary))));
+    }
+    samples.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.labels.cmp(&b.labels)));
+
+    snapshot.time = time.elapsed_secs_f64();
+    snapshot.samples = samples;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DumpFormat {
+    Csv,
+    Json,
+    Prometheus,
+}
+
+impl DumpFormat {
+    pub const ALL: [DumpFormat; 3] = [DumpFormat::Csv, DumpFormat::Json, DumpFormat::Prometheus];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DumpFormat::Csv => "CSV",
+            DumpFormat::Json => "JSON",
+            DumpFormat::Prometheus => "Prometheus",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            DumpFormat::Csv => "csv",
+            DumpFormat::Json => "json",
+            DumpFormat::Prometheus => "prom",
+        }
+    }
+
+    pub fn render(self, snapshot: &MetricsSnapshot) -> String {
+        match self {
+            DumpFormat::Csv => render_csv(snapshot),
+            DumpFormat::Json => render_json(snapshot),
+            DumpFormat::Prometheus => render_prometheus(snapshot),
+        }
+    }
+}
+
+/// Asks for the latest snapshot to be written to `MetricsSettings::dump_dir`.
+#[derive(Message, Clone, Copy, Debug)]
+pub struct DumpMetrics(pub DumpFormat);
+
+fn dump_metrics(
+    mut requests: MessageReader<DumpMetrics>,
+    settings: Res<MetricsSettings>,
+    snapshot: Res<MetricsSnapshot>,
+) {
+    for DumpMetrics(format) in requests.read() {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis());
+        let path = settings
+            .dump_dir
+            .join(format!("metrics-{stamp}.{}", format.extension()));
+        let written = std::fs::create_dir_all(&settings.dump_dir)
+            .and_then(|()| std::fs::write(&path, format.render(&snapshot)));
+        match written {
+            Ok(()) => info!("Wrote {} metrics to {}", format.name(), path.display()),
+            Err(error) => warn!("Couldn't write metrics to {}: {error}", path.display()),
+        }
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn joined_labels(labels: &[(String, String)]) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn render_csv(snapshot: &MetricsSnapshot) -> String {
+    let mut out = String::from("name,labels,kind,value,count,sum,min,max,p50,p90,p99\n");
+    for sample in &snapshot.samples {
+        let _ = write!(
+            out,
+            "{},{},{},",
+            csv_field(&sample.name),
+            csv_field(&joined_labels(&sample.labels)),
+            sample.value.kind(),
+        );
+        let _ = match sample.value {
+            MetricValue::Counter(value) => writeln!(out, "{value},,,,,,,"),
+            MetricValue::Gauge(value) => writeln!(out, "{value},,,,,,,"),
+            MetricValue::Histogram(stats) => writeln!(
+                out,
+                ",{},{},{},{},{},{},{}",
+                stats.count, stats.sum, stats.min, stats.max, stats.p50, stats.p90, stats.p99,
+            ),
+        };
+    }
+    out
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn render_json(snapshot: &MetricsSnapshot) -> String {
+    let metrics: Vec<String> = snapshot
+        .samples
+        .iter()
+        .map(|sample| {
+            let labels: Vec<String> = sample
+                .labels
+                .iter()
+                .map(|(key, value)| format!("{}:{}", json_string(key), json_string(value)))
+                .collect();
+            let value = match sample.value {
+                MetricValue::Counter(value) => format!("\"value\":{value}"),
+                MetricValue::Gauge(value) => format!("\"value\":{}", json_number(value)),
+                MetricValue::Histogram(stats) => format!(
+                    "\"count\":{},\"sum\":{},\"min\":{},\"max\":{},\"p50\":{},\"p90\":{},\"p99\":{}",
+                    stats.count,
+                    json_number(stats.sum),
+                    json_number(stats.min),
+                    json_number(stats.max),
+                    json_number(stats.p50),
+                    json_number(stats.p90),
+                    json_number(stats.p99),
+                ),
+            };
+            format!(
+                "{{\"name\":{},\"kind\":\"{}\",\"labels\":{{{}}},{value}}}",
+                json_string(&sample.name),
+                sample.value.kind(),
+                labels.join(","),
+            )
+        })
+        .collect();
+    format!("{{\"time\":{},\"metrics\":[{}]}}\n", json_number(snapshot.time), metrics.join(","))
+}
+
+/// Prometheus only allows `[a-zA-Z0-9_:]` in names.
+fn prometheus_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == ':' { c } else { '_' })
+        .collect()
+}
+
+fn prometheus_labels(labels: &[(String, String)], extra: Option<(&str, &str)>) -> String {
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .chain(extra)
+        .map(|(key, value)| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            format!("{}=\"{value}\"", prometheus_name(key))
+        })
+        .collect();
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn render_prometheus(snapshot: &MetricsSnapshot) -> String {
+    let mut out = String::new();
+    let mut typed: Option<&str> = None;
+    for sample in &snapshot.samples {
+        let name = prometheus_name(&sample.name);
+        // Samples are sorted by name, so each family is contiguous.
+        if typed != Some(sample.name.as_str()) {
+            let kind = match sample.value {
+                MetricValue::Histogram(_) => "summary",
+                value => value.kind(),
+            };
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            typed = Some(&sample.name);
+        }
+        let _ = match sample.value {
+            MetricValue::Counter(value) => writeln!(out, "{name}{} {value}", prometheus_labels(&sample.labels, None)),
+            MetricValue::Gauge(value) => writeln!(out, "{name}{} {value}", prometheus_labels(&sample.labels, None)),
+            MetricValue::Histogram(stats) => {
+                for (quantile, value) in [("0.5", stats.p50), ("0.9", stats.p90), ("0.99", stats.p99)] {
+                    let labels = prometheus_labels(&sample.labels, Some(("quantile", quantile)));
+                    let _ = writeln!(out, "{name}{labels} {value}");
+                }
+                let labels = prometheus_labels(&sample.labels, None);
+                let _ = writeln!(out, "{name}_sum{labels} {}", stats.sum);
+                writeln!(out, "{name}_count{labels} {}", stats.count)
+            }
+        };
+    }
+    out
+}
+
+fn metrics_panel(
+    mut contexts: EguiContexts,
+    snapshot: Res<MetricsSnapshot>,
+    names: Query<&Name>,
+    mut dumps: MessageWriter<DumpMetrics>,
+    mut filter: Local<String>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    egui::Window::new("📊 Metrics").default_open(false).show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Dump:");
+            for format in DumpFormat::ALL {
+                if ui.button(format.name()).clicked() {
+                    dumps.write(DumpMetrics(format));
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Filter:");
+            ui.text_edit_singleline(&mut *filter);
+        });
+        ui.label(format!("{} series at {:.1}s", snapshot.samples.len(), snapshot.time));
+
+        let shown = |sample: &&MetricSample| sample.name.contains(filter.as_str());
+        let mut per_entity: Vec<(Entity, &MetricSample)> = Vec::new();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("metrics_global").striped(true).show(ui, |ui| {
+                for sample in snapshot.samples.iter().filter(shown) {
+                    if let Some(entity) = sample.entity() {
+                        per_entity.push((entity, sample));
+                        continue;
+                    }
+                    ui.label(&sample.name);
+                    ui.label(joined_labels(&sample.labels));
+                    ui.label(sample.value.to_string());
+                    ui.end_row();
+                }
+            });
+
+            per_entity.sort_by_key(|(entity, _)| *entity);
+            for group in per_entity.chunk_by(|a, b| a.0 == b.0) {
+                let entity = group[0].0;
+                let title = match names.get(entity) {
+                    Ok(name) => format!("{name} ({entity})"),
+                    Err(_) => entity.to_string(),
+                };
+                egui::CollapsingHeader::new(title).id_salt(entity).show(ui, |ui| {
+                    egui::Grid::new(("metrics_entity", entity)).striped(true).show(ui, |ui| {
+                        for (_, sample) in group {
+                            let labels: Vec<(String, String)> = sample
+                                .labels
+                                .iter()
+                                .filter(|(key, _)| key != ENTITY_LABEL)
+                                .cloned()
+                                .collect();
+                            ui.label(&sample.name);
+                            ui.label(joined_labels(&labels));
+                            ui.label(sample.value.to_string());
+                            ui.end_row();
+                        }
+                    });
+                });
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> MetricsSnapshot {
+        let ship = Entity::from_raw_u32(3).unwrap();
+        MetricsSnapshot {
+            time: 1.5,
+            samples: vec![
+                MetricSample {
+                    name: "ai.score_dist".into(),
+                    labels: Vec::new(),
+                    value: MetricValue::Histogram(HistogramStats {
+                        count: 2,
+                        sum: 1.0,
+                        min: 0.25,
+                        max: 0.75,
+                        p50: 0.25,
+                        p90: 0.75,
+                        p99: 0.75,
+                    }),
+                },
+                MetricSample {
+                    name: "ai.threat_score".into(),
+                    labels: vec![(ENTITY_LABEL.into(), entity_label(ship))],
+                    value: MetricValue::Gauge(0.5),
+                },
+                MetricSample {
+                    name: "weapons.pool.grown".into(),
+                    labels: vec![("kind".into(), "say \"hi\", ok".into())],
+                    value: MetricValue::Counter(4),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn entity_labels_round_trip() {
+        let ship = Entity::from_raw_u32(3).unwrap();
+        assert_eq!(snapshot().samples[1].entity(), Some(ship));
+        assert_eq!(snapshot().samples[0].entity(), None);
+    }
+
+    #[test]
+    fn dumps_escape_and_name_things_properly() {
+        let snapshot = snapshot();
+        let prometheus = render_prometheus(&snapshot);
+        assert!(prometheus.contains("# TYPE ai_score_dist summary\n"));
+        assert!(prometheus.contains("ai_score_dist{quantile=\"0.99\"} 0.75\n"));
+        assert!(prometheus.contains("ai_score_dist_count 2\n"));
+        assert!(prometheus.contains("weapons_pool_grown{kind=\"say \\\"hi\\\", ok\"} 4\n"));
+
+        let csv = render_csv(&snapshot);
+        assert!(csv.contains("weapons.pool.grown,\"kind=say \"\"hi\"\", ok\",counter,4,"));
+        assert_eq!(csv.lines().count(), 4);
+
+        let json = render_json(&snapshot);
+        assert!(json.starts_with("{\"time\":1.5,"));
+        assert!(json.contains("\"labels\":{\"kind\":\"say \\\"hi\\\", ok\"}"));
+    }
+}
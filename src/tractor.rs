@@ -0,0 +1,494 @@
+//! Tractor beam: a non-lethal tool that grabs whatever rigid body is in
+//! front of the ship and holds it at a distance with a damped spring.
+//!
+//! Holding `TractorBeam::engaged` ray casts along the ship's nose until it
+//! hits a body, then locks on until released or stretched past breaking
+//! range. The spring acts on both ends — pulling the target in and the
+//! ship out — so which one moves depends on their masses: debris comes to
+//! you, a freighter drags you. Dynamic bodies get a real avian force;
+//! kinematic ships (the AI) get the equivalent velocity change using
+//! `Ship::mass`. AI movement rewrites `LinearVelocity` every frame, so that
+//! change goes into a separate `TractorDrift` which moves the ship on top
+//! of whatever it's doing, and bleeds off once the beam lets go.
+//!
+//! The player holds R to engage. AI ships with a beam weigh the `tractor`
+//! action on `TractorScore`, how squarely their target sits in front of
+//! the beam; while it runs they hold still with the nose on the
+//! target and the beam engaged.
+
+use std::time::Instant;
+
+use bevy::color::palettes::css::{AQUA, LIGHT_CYAN};
+use bevy::ecs::query::QueryData;
+use bevy::prelude::*;
+use bevy::utils::Parallel;
+
+use avian3d::prelude::{
+    Forces, Position, ReadRigidBodyForces, RigidBody, SpatialQuery, SpatialQueryFilter,
+    WriteRigidBodyForces,
+};
+
+use crate::ai::{Action, AiLod, AiLodStats, AiSet, AiTarget, Scorer, UtilityAiAppExt};
+use crate::combat::{ActingAgents, AiEnabled, AiMarker, Mines, Ship, SteeringContext, gather_mines};
+use crate::common::Player;
+use crate::steering::{self, Kinematics};
+use crate::weapons::Projectile;
+
+pub struct TractorPlugin;
+
+impl Plugin for TractorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TractorSettings>()
+            .register_required_components::<Ship, TractorDrift>()
+            .register_ai_scorer::<TractorScore>()
+            .register_ai_action::<Tractor>()
+            .add_systems(PreUpdate, tractor_scorer_system.in_set(AiSet::Scorers))
+            .add_systems(Update, tractor_action_system.in_set(AiSet::Actions))
+            .add_systems(
+                Update,
+                (
+                    read_player_tractor_input,
+                    release_ai_tractors,
+                    acquire_tractor_targets.after(AiSet::Actions),
+                    draw_tractor_beams,
+                )
+                    .chain(),
+            )
+            .add_systems(FixedUpdate, (apply_tractor_forces, apply_tractor_drift).chain());
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct TractorSettings {
+    /// Exponential decay rate (per second) of a kinematic ship's
+    /// `TractorDrift`.
+    pub drift_damping: f32,
+    /// AI only weighs tractoring a target within this many radians of its
+    /// nose.
+    pub ai_cone: f32,
+}
+
+impl Default for TractorSettings {
+    fn default() -> Self {
+        Self {
+            drift_damping: 1.5,
+            ai_cone: 0.3,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct TractorBeam {
+    /// Set by whoever flies the ship while the beam should be on.
+    pub engaged: bool,
+    pub target: Option<Entity>,
+    /// Reach of the acquisition ray.
+    pub range: f32,
+    /// The hold snaps once the target drifts past `range * break_factor`.
+    pub break_factor: f32,
+    /// Distance the spring tries to hold the target at.
+    pub hold_distance: f32,
+    /// Spring force per unit of stretch.
+    pub stiffness: f32,
+    /// Force per unit of closing speed, so the hold settles instead of
+    /// oscillating.
+    pub damping: f32,
+    pub max_force: f32,
+}
+
+impl Default for TractorBeam {
+    fn default() -> Self {
+        Self {
+            engaged: false,
+            target: None,
+            range: 40.0,
+            break_factor: 1.5,
+            hold_distance: 8.0,
+            stiffness: 30.0,
+            damping: 12.0,
+            max_force: 400.0,
+        }
+    }
+}
+
+impl TractorBeam {
+    /// Spring force along the beam at `distance`, with the two ends
+    /// separating at `separation_speed`. Negative pulls them together.
+    pub fn spring_force(&self, distance: f32, separation_speed: f32) -> f32 {
+        let force = -self.stiffness * (distance - self.hold_distance) - self.damping * separation_speed;
+        force.clamp(-self.max_force, self.max_force)
+    }
+
+    /// How squarely `target` sits in front of a beam at `origin` facing
+    /// `forward`: 1 dead ahead at point blank, falling to 0 at `range` or
+    /// `cone` radians off the nose.
+    pub fn alignment(&self, origin: Vec3, forward: Vec3, target: Vec3, cone: f32) -> f32 {
+        let offset = target - origin;
+        let distance = offset.length();
+        if distance < 1e-3 || distance > self.range || cone <= 0.0 {
+            return 0.0;
+        }
+        let angle = forward.angle_between(offset);
+        ((1.0 - distance / self.range) * (1.0 - angle / cone)).max(0.0)
+    }
+}
+
+/// Velocity a tractor beam has put into a kinematic ship, kept apart from
+/// its `LinearVelocity` so AI movement can't overwrite it. Every `Ship`
+/// gets one; only kinematic bodies use it.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct TractorDrift(pub Vec3);
+
+/// See `TractorBeam::alignment`.
+#[derive(Component, Default)]
+pub struct TractorScore(f32);
+
+impl Scorer for TractorScore {
+    const NAME: &'static str = "tractor";
+
+    fn value(&self) -> f32 {
+        self.0
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Tractor;
+
+impl Action for Tractor {
+    const NAME: &'static str = "tractor";
+}
+
+/// Either end of a tractor beam.
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct TractorBody {
+    rigid_body: &'static RigidBody,
+    forces: Forces,
+    ship: Option<&'static Ship>,
+    drift: Option<&'static mut TractorDrift>,
+}
+
+impl TractorBodyItem<'_, '_> {
+    fn position(&self) -> Vec3 {
+        self.forces.position().0
+    }
+
+    fn velocity(&self) -> Vec3 {
+        self.forces.linear_velocity() + self.drift.as_ref().map_or(Vec3::ZERO, |drift| drift.0)
+    }
+
+    /// Applies `force` for one step of `dt`. Static bodies, and kinematic
+    /// bodies with no `Ship` mass to go by, don't budge.
+    fn pull(&mut self, force: Vec3, dt: f32) {
+        match (self.rigid_body, self.ship, self.drift.as_mut()) {
+            (RigidBody::Dynamic, _, _) => self.forces.apply_force(force),
+            (RigidBody::Kinematic, Some(ship), Some(drift)) => {
+                drift.0 += force / ship.mass.max(1e-3) * dt;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn read_player_tractor_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut player: Query<&mut TractorBeam, With<Player>>,
+) {
+    if let Ok(mut beam) = player.single_mut() {
+        beam.engaged = keys.pressed(KeyCode::KeyR);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn tractor_scorer_system(
+    settings: Res<TractorSettings>,
+    mut query: Query<(&Transform, &AiTarget, &TractorBeam, Option<&AiLod>, &mut TractorScore), With<AiMarker>>,
+    mut stats: ResMut<AiLodStats>,
+    mut local_stats: Local<Parallel<AiLodStats>>,
+) {
+    query.par_iter_mut().for_each(|(transform, target, beam, lod, mut score)| {
+        if lod.is_some_and(|lod| !lod.thinking()) {
+            return;
+        }
+        let started = Instant::now();
+        score.0 = target
+            .last_known_position
+            .filter(|_| target.in_contact)
+            .map_or(0.0, |position| {
+                beam.alignment(transform.translation, *transform.forward(), position, settings.ai_cone)
+            });
+        if let Some(lod) = lod {
+            local_stats.scope(|stats| stats.record(lod.tier, started));
+        }
+    });
+    stats.absorb(&mut local_stats);
+}
+
+/// Brakes and holds the nose on the target with the beam engaged, so the
+/// beam does the pulling. Still keeps clear of ships, mines and obstacles.
+fn tractor_action_system(
+    mut agents: ParamSet<(ActingAgents<Tractor>, Mines)>,
+    mut beams: Query<&mut TractorBeam>,
+    steering: SteeringContext,
+) {
+    let mines = gather_mines(&agents.p1());
+    let settings = &steering.settings;
+    let dt = steering.time.delta_secs();
+
+    for (entity, faction, target, transform, mut linvel, mut heading, subsystems, limits, _) in &mut agents.p0() {
+        let Ok(mut beam) = beams.get_mut(entity) else {
+            continue;
+        };
+        let limits = steering.limits(limits, subsystems);
+        let agent = Kinematics {
+            position: transform.translation,
+            velocity: linvel.0,
+        };
+        let brake = steering::arrive(&agent, agent.position, settings.slowing_radius, limits);
+        let acceleration = steering
+            .hazards(entity, *faction, &agent, &mines, limits)
+            .add(brake, 1.0)
+            .finish(limits);
+        linvel.0 = steering::integrate(linvel.0, acceleration, dt, limits);
+
+        if let Some(position) = target.last_known_position {
+            heading.0 = Some(position - agent.position);
+        }
+        beam.engaged = target.in_contact;
+    }
+}
+
+/// Lets go with AI ships that have moved on from "tractor", or whose AI is
+/// off.
+fn release_ai_tractors(
+    enabled: Res<AiEnabled>,
+    mut beams: Query<(&mut TractorBeam, Has<Tractor>), With<AiMarker>>,
+) {
+    for (mut beam, tractoring) in &mut beams {
+        if beam.engaged && (!enabled.0 || !tractoring) {
+            beam.engaged = false;
+        }
+    }
+}
+
+/// Locks engaged beams onto the first rigid body along the ship's nose and
+/// drops holds that were released, lost, or stretched too far.
+fn acquire_tractor_targets(
+    spatial: SpatialQuery,
+    mut beams: Query<(Entity, &Transform, &mut TractorBeam)>,
+    bodies: Query<&Position, With<RigidBody>>,
+    projectiles: Query<(), With<Projectile>>,
+) {
+    for (entity, transform, mut beam) in &mut beams {
+        if !beam.engaged {
+            beam.target = None;
+            continue;
+        }
+
+        if let Some(target) = beam.target {
+            let held = bodies.get(target).is_ok_and(|position| {
+                position.0.distance(transform.translation) <= beam.range * beam.break_factor
+            });
+            if !held {
+                debug!("Tractor hold on {target} broken");
+                beam.target = None;
+            }
+            continue;
+        }
+
+        let hit = spatial.cast_ray_predicate(
+            transform.translation,
+            transform.forward(),
+            beam.range,
+            true,
+            &SpatialQueryFilter::from_excluded_entities([entity]),
+            &|hit| bodies.contains(hit) && !projectiles.contains(hit),
+        );
+        if let Some(hit) = hit {
+            debug!("Tractor locked onto {}", hit.entity);
+            beam.target = Some(hit.entity);
+        }
+    }
+}
+
+fn apply_tractor_forces(
+    time: Res<Time<Fixed>>,
+    beams: Query<(Entity, &TractorBeam)>,
+    mut bodies: Query<TractorBody>,
+) {
+    let dt = time.delta_secs();
+    for (entity, beam) in &beams {
+        let Some(target) = beam.target else {
+            continue;
+        };
+        let Ok([mut user, mut held]) = bodies.get_many_mut([entity, target]) else {
+            continue;
+        };
+        let offset = held.position() - user.position();
+        let distance = offset.length();
+        let Some(direction) = offset.try_normalize() else {
+            continue;
+        };
+        let separation_speed = (held.velocity() - user.velocity()).dot(direction);
+        let force = direction * beam.spring_force(distance, separation_speed);
+        held.pull(force, dt);
+        user.pull(-force, dt);
+    }
+}
+
+/// Moves kinematic ships by their `TractorDrift` and bleeds it off.
+fn apply_tractor_drift(
+    time: Res<Time<Fixed>>,
+    settings: Res<TractorSettings>,
+    mut bodies: Query<(&RigidBody, &mut Position, &mut TractorDrift)>,
+) {
+    let dt = time.delta_secs();
+    let decay = (-settings.drift_damping * dt).exp();
+    for (rigid_body, mut position, mut drift) in &mut bodies {
+        if *rigid_body != RigidBody::Kinematic || drift.0 == Vec3::ZERO {
+            continue;
+        }
+        position.0 += drift.0 * dt;
+        drift.0 *= decay;
+        if drift.0.length_squared() < 1e-6 {
+            drift.0 = Vec3::ZERO;
+        }
+    }
+}
+
+fn draw_tractor_beams(
+    mut gizmos: Gizmos,
+    beams: Query<(&Transform, &TractorBeam)>,
+    targets: Query<&Transform>,
+) {
+    for (transform, beam) in &beams {
+        if !beam.engaged {
+            continue;
+        }
+        match beam.target.and_then(|target| targets.get(target).ok()) {
+            Some(target) => gizmos.line(transform.translation, target.translation, AQUA),
+            None => gizmos.line(
+                transform.translation,
+                transform.translation + transform.forward() * beam.range,
+                LIGHT_CYAN.with_alpha(0.3),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use avian3d::dynamics::integrator::VelocityIntegrationData;
+    use avian3d::prelude::LinearVelocity;
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::faction::Faction;
+    use crate::steering::DesiredHeading;
+
+    #[test]
+    fn spring_pulls_in_when_stretched_and_pushes_out_when_close() {
+        let beam = TractorBeam::default();
+        assert!(beam.spring_force(beam.hold_distance + 5.0, 0.0) < 0.0);
+        assert!(beam.spring_force(beam.hold_distance - 5.0, 0.0) > 0.0);
+        assert_eq!(beam.spring_force(beam.hold_distance, 0.0), 0.0);
+    }
+
+    #[test]
+    fn spring_is_damped_and_clamped() {
+        let beam = TractorBeam::default();
+        // Closing fast at the hold distance: damping pushes back out.
+        assert!(beam.spring_force(beam.hold_distance, -3.0) > 0.0);
+        assert_eq!(beam.spring_force(1_000.0, 0.0), -beam.max_force);
+    }
+
+    #[test]
+    fn alignment_favours_close_targets_dead_ahead() {
+        let beam = TractorBeam::default();
+        let score = |target: Vec3| beam.alignment(Vec3::ZERO, Vec3::NEG_Z, target, 0.3);
+        let close = score(Vec3::new(0.0, 0.0, -10.0));
+        let far = score(Vec3::new(0.0, 0.0, -30.0));
+        assert!(close > far && far > 0.0);
+        // Off the nose, or out of reach.
+        assert_eq!(score(Vec3::new(10.0, 0.0, -10.0)), 0.0);
+        assert_eq!(score(Vec3::new(0.0, 0.0, -50.0)), 0.0);
+    }
+
+    #[test]
+    fn ai_engages_its_beam_on_the_target_and_lets_go_after() {
+        let mut world = crate::combat::test_world();
+        world.insert_resource(AiEnabled(true));
+        let target = world.spawn((Transform::from_xyz(0.0, 0.0, -20.0), Faction::Player)).id();
+        let mut ai_target = AiTarget::default();
+        ai_target.entity = Some(target);
+        ai_target.last_known_position = Some(Vec3::new(0.0, 0.0, -20.0));
+        ai_target.in_contact = true;
+        let agent = world
+            .spawn((
+                AiMarker,
+                Faction::Raiders,
+                ai_target,
+                Transform::from_xyz(0.0, 10.0, 0.0),
+                LinearVelocity::default(),
+                TractorBeam::default(),
+                Tractor,
+            ))
+            .id();
+
+        world.run_system_once(tractor_action_system).unwrap();
+        assert!(world.get::<TractorBeam>(agent).unwrap().engaged);
+        let heading = world.get::<DesiredHeading>(agent).unwrap().0.unwrap();
+        assert!(heading.normalize().dot(Vec3::new(0.0, -10.0, -20.0).normalize()) > 0.99);
+
+        // The picker moved on.
+        world.entity_mut(agent).remove::<Tractor>();
+        world.run_system_once(release_ai_tractors).unwrap();
+        assert!(!world.get::<TractorBeam>(agent).unwrap().engaged);
+    }
+
+    #[test]
+    fn pull_moves_a_kinematic_ship_whose_velocity_is_overwritten() {
+        let mut world = World::new();
+        world.init_resource::<TractorSettings>();
+        let mut time = Time::<Fixed>::default();
+        time.advance_by(Duration::from_secs_f32(1.0 / 64.0));
+        world.insert_resource(time);
+        let ship = |position: Vec3| {
+            (
+                RigidBody::Kinematic,
+                Position(position),
+                Ship { health: 100.0, max_health: 100.0, mass: 5.0 },
+                TractorDrift::default(),
+                VelocityIntegrationData::default(),
+            )
+        };
+        let held = world.spawn(ship(Vec3::new(0.0, 0.0, -30.0))).id();
+        let user = world
+            .spawn((
+                ship(Vec3::ZERO),
+                TractorBeam {
+                    engaged: true,
+                    target: Some(held),
+                    ..default()
+                },
+            ))
+            .id();
+
+        for _ in 0..64 {
+            // AI movement writes both ships' velocity every frame.
+            for entity in [held, user] {
+                world.get_mut::<LinearVelocity>(entity).unwrap().0 = Vec3::ZERO;
+            }
+            world.run_system_once(apply_tractor_forces).unwrap();
+            world.run_system_once(apply_tractor_drift).unwrap();
+        }
+        let distance = world
+            .get::<Position>(held)
+            .unwrap()
+            .0
+            .distance(world.get::<Position>(user).unwrap().0);
+        assert!(distance < 20.0, "still {distance} apart");
+    }
+}
@@ -17,5 +17,5 @@ pub fn generate_targets(len: usize) -> Box<[(Vec3, Color, String)]> {
         vec.push((position, color, name));
     }
 
-    return vec.into_boxed_slice();
+    vec.into_boxed_slice()
 }
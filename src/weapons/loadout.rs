@@ -0,0 +1,443 @@
+//! Weapon loadouts: what a ship carries, how each weapon fires, and which
+//! fire action triggers it.
+//!
+//! A ship's `WeaponLoadout` is a list of `Weapon`s (a projectile kind, a
+//! `FireMode` and a hardpoint offset) plus `WeaponGroup`s binding subsets
+//! of them to a `FireAction`. Triggers come from the ship's `FireInput`,
+//! which the player's mouse writes and AI can write just the same, so one
+//! generic firing system covers every ship and every weapon.
+//!
+//! Everything here is plain state and trigger logic; spawning the actual
+//! projectiles happens in `weapons::fire_weapons`.
+
+use std::ops::BitOrAssign;
+
+use bevy::prelude::*;
+
+use super::ProjectileKind;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FireAction {
+    Primary,
+    Secondary,
+    Tertiary,
+    /// Deployables and tools rather than guns.
+    Utility,
+}
+
+impl FireAction {
+    pub const ALL: [FireAction; 4] = [
+        FireAction::Primary,
+        FireAction::Secondary,
+        FireAction::Tertiary,
+        FireAction::Utility,
+    ];
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FireMode {
+    /// Fires every cooldown while held.
+    Automatic,
+    /// One shot per press.
+    SemiAuto,
+    /// One press fires `count` shots `interval` seconds apart; the cooldown
+    /// starts after the last one.
+    Burst { count: u32, interval: f32 },
+    /// Hold to charge for up to `max_time`, release to fire. Damage and
+    /// projectile speed scale linearly from 1 up to the given maxima.
+    Charge {
+        max_time: f32,
+        max_damage_scale: f32,
+        max_speed_scale: f32,
+    },
+    /// Lock-on weapons: hold to build a missile lock, release to launch
+    /// `count` shots `interval` seconds apart at the locked target. Won't
+    /// fire without a lock.
+    Salvo { count: u32, interval: f32 },
+}
+
+/// The state of one fire action this frame.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Trigger {
+    pub pressed: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+}
+
+impl BitOrAssign for Trigger {
+    fn bitor_assign(&mut self, other: Self) {
+        self.pressed |= other.pressed;
+        self.just_pressed |= other.just_pressed;
+        self.just_released |= other.just_released;
+    }
+}
+
+/// Per-ship fire action state. Whoever drives the ship (player input or
+/// AI) calls `set` for every action once per frame; edges are derived from
+/// the previous call.
+#[derive(Component, Default, Debug)]
+pub struct FireInput {
+    pressed: [bool; FireAction::ALL.len()],
+    previous: [bool; FireAction::ALL.len()],
+}
+
+impl FireInput {
+    pub fn set(&mut self, action: FireAction, pressed: bool) {
+        let index = action as usize;
+        self.previous[index] = self.pressed[index];
+        self.pressed[index] = pressed;
+    }
+
+    pub fn trigger(&self, action: FireAction) -> Trigger {
+        let index = action as usize;
+        let (now, before) = (self.pressed[index], self.previous[index]);
+        Trigger {
+            pressed: now,
+            just_pressed: now && !before,
+            just_released: !now && before,
+        }
+    }
+}
+
+/// Damage and speed multipliers for a single shot; only charge weapons
+/// ever deviate from 1.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ShotScale {
+    pub damage: f32,
+    pub speed: f32,
+}
+
+impl ShotScale {
+    pub const FULL: ShotScale = ShotScale {
+        damage: 1.0,
+        speed: 1.0,
+    };
+}
+
+#[derive(Clone, Debug)]
+pub struct Weapon {
+    pub kind: ProjectileKind,
+    pub mode: FireMode,
+    /// Muzzle position in ship-local space.
+    pub offset: Vec3,
+    /// Homing target for the salvo in progress (lock-on weapons only).
+    pub target: Option<Entity>,
+    cooldown_timer: f32,
+    /// Shots left in the current burst or salvo, and time to the next.
+    sequence_remaining: u32,
+    sequence_timer: f32,
+    charge: f32,
+}
+
+impl Weapon {
+    pub fn new(kind: ProjectileKind, mode: FireMode) -> Self {
+        Self {
+            kind,
+            mode,
+            offset: Vec3::NEG_Z,
+            target: None,
+            cooldown_timer: 0.0,
+            sequence_remaining: 0,
+            sequence_timer: 0.0,
+            charge: 0.0,
+        }
+    }
+
+    pub fn with_offset(mut self, offset: Vec3) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Charge progress in `0..=1` for charge weapons, zero otherwise.
+    pub fn charge_fraction(&self) -> f32 {
+        match self.mode {
+            FireMode::Charge { max_time, .. } => (self.charge / max_time.max(1e-3)).min(1.0),
+            _ => 0.0,
+        }
+    }
+
+    pub fn is_firing_sequence(&self) -> bool {
+        self.sequence_remaining > 0
+    }
+
+    /// Fires the first shot of a `count`-shot sequence now and schedules
+    /// the rest.
+    fn start_sequence(&mut self, count: u32, interval: f32, cooldown: f32) -> ShotScale {
+        self.sequence_remaining = count.saturating_sub(1);
+        self.sequence_timer = interval;
+        if self.sequence_remaining == 0 {
+            self.cooldown_timer = cooldown;
+        }
+        ShotScale::FULL
+    }
+
+    /// Advances the weapon by `dt` under `trigger`, returning the shot to
+    /// fire this frame, if any. `armed` gates the start of a salvo (i.e.
+    /// whether a lock is held); a sequence already under way always
+    /// finishes.
+    pub fn tick(&mut self, dt: f32, trigger: Trigger, cooldown: f32, armed: bool) -> Option<ShotScale> {
+        self.cooldown_timer -= dt;
+
+        if self.sequence_remaining > 0 {
+            self.sequence_timer -= dt;
+            if self.sequence_timer > 0.0 {
+                return None;
+            }
+            let interval = match self.mode {
+                FireMode::Burst { interval, .. } | FireMode::Salvo { interval, .. } => interval,
+                _ => 0.0,
+            };
+            self.sequence_remaining -= 1;
+            self.sequence_timer = interval;
+            if self.sequence_remaining == 0 {
+                self.cooldown_timer = cooldown;
+            }
+            return Some(ShotScale::FULL);
+        }
+
+        let ready = self.cooldown_timer <= 0.0;
+        match self.mode {
+            FireMode::Automatic => (trigger.pressed && ready).then(|| {
+                self.cooldown_timer = cooldown;
+                ShotScale::FULL
+            }),
+            FireMode::SemiAuto => (trigger.just_pressed && ready).then(|| {
+                self.cooldown_timer = cooldown;
+                ShotScale::FULL
+            }),
+            FireMode::Burst { count, interval } => (trigger.just_pressed && ready)
+                .then(|| self.start_sequence(count, interval, cooldown)),
+            FireMode::Salvo { count, interval } => (trigger.just_released && ready && armed)
+                .then(|| self.start_sequence(count, interval, cooldown)),
+            FireMode::Charge {
+                max_time,
+                max_damage_scale,
+                max_speed_scale,
+            } => {
+                if trigger.pressed && ready {
+                    self.charge = (self.charge + dt).min(max_time);
+                    return None;
+                }
+                if !trigger.just_released || self.charge <= 0.0 {
+                    self.charge = 0.0;
+                    return None;
+                }
+                let t = self.charge_fraction();
+                self.charge = 0.0;
+                self.cooldown_timer = cooldown;
+                Some(ShotScale {
+                    damage: 1.0 + (max_damage_scale - 1.0) * t,
+                    speed: 1.0 + (max_speed_scale - 1.0) * t,
+                })
+            }
+        }
+    }
+}
+
+/// Weapons, by index into `WeaponLoadout::weapons`, fired together by one
+/// action. A linked group fires every weapon at once; an unlinked one
+/// passes the trigger along, one weapon per shot.
+#[derive(Clone, Debug)]
+pub struct WeaponGroup {
+    pub action: FireAction,
+    pub weapons: Vec<usize>,
+    pub linked: bool,
+    cursor: usize,
+}
+
+impl WeaponGroup {
+    pub fn linked(action: FireAction, weapons: impl Into<Vec<usize>>) -> Self {
+        Self {
+            action,
+            weapons: weapons.into(),
+            linked: true,
+            cursor: 0,
+        }
+    }
+
+    pub fn alternating(action: FireAction, weapons: impl Into<Vec<usize>>) -> Self {
+        Self {
+            linked: false,
+            ..Self::linked(action, weapons)
+        }
+    }
+
+    /// Weapons that receive this group's trigger this frame.
+    fn armed_weapons(&self) -> &[usize] {
+        if self.linked || self.weapons.is_empty() {
+            &self.weapons
+        } else {
+            let index = self.cursor % self.weapons.len();
+            &self.weapons[index..=index]
+        }
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct WeaponLoadout {
+    pub weapons: Vec<Weapon>,
+    pub groups: Vec<WeaponGroup>,
+}
+
+/// The classic layout: turret on primary, laser on secondary, a single
+/// lock-on missile on tertiary, and a mine dropped astern on utility.
+impl Default for WeaponLoadout {
+    fn default() -> Self {
+        Self {
+            weapons: vec![
+                Weapon::new(ProjectileKind::Turret, FireMode::Automatic),
+                Weapon::new(ProjectileKind::Laser, FireMode::Automatic),
+                Weapon::new(
+                    ProjectileKind::Missile,
+                    FireMode::Salvo {
+                        count: 1,
+                        interval: 0.0,
+                    },
+                ),
+                Weapon::new(ProjectileKind::Mine, FireMode::SemiAuto).with_offset(Vec3::Z * 2.0),
+            ],
+            groups: vec![
+                WeaponGroup::linked(FireAction::Primary, [0]),
+                WeaponGroup::linked(FireAction::Secondary, [1]),
+                WeaponGroup::linked(FireAction::Tertiary, [2]),
+                WeaponGroup::linked(FireAction::Utility, [3]),
+            ],
+        }
+    }
+}
+
+impl WeaponLoadout {
+    /// Each weapon's combined trigger this frame across every group it
+    /// belongs to.
+    pub fn triggers(&self, input: &FireInput) -> Vec<Trigger> {
+        let mut triggers = vec![Trigger::default(); self.weapons.len()];
+        for group in &self.groups {
+            let trigger = input.trigger(group.action);
+            for &weapon in group.armed_weapons() {
+                if let Some(slot) = triggers.get_mut(weapon) {
+                    *slot |= trigger;
+                }
+            }
+        }
+        triggers
+    }
+
+    /// Moves every unlinked group whose current weapon just fired on to its
+    /// next weapon.
+    pub fn advance_groups(&mut self, fired: &[usize]) {
+        for group in self.groups.iter_mut().filter(|group| !group.linked) {
+            if group
+                .armed_weapons()
+                .first()
+                .is_some_and(|weapon| fired.contains(weapon))
+            {
+                group.cursor = group.cursor.wrapping_add(1);
+            }
+        }
+    }
+
+    /// Whether any lock-on weapon is bound to an action currently held.
+    pub fn lock_held(&self, input: &FireInput) -> bool {
+        self.groups.iter().any(|group| {
+            input.trigger(group.action).pressed
+                && group.weapons.iter().any(|&index| {
+                    self.weapons
+                        .get(index)
+                        .is_some_and(|weapon| matches!(weapon.mode, FireMode::Salvo { .. }))
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 0.05;
+
+    fn held() -> Trigger {
+        Trigger {
+            pressed: true,
+            ..default()
+        }
+    }
+
+    fn pressed() -> Trigger {
+        Trigger {
+            pressed: true,
+            just_pressed: true,
+            ..default()
+        }
+    }
+
+    fn released() -> Trigger {
+        Trigger {
+            just_released: true,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn burst_fires_count_shots_from_one_press() {
+        let mut weapon = Weapon::new(
+            ProjectileKind::Turret,
+            FireMode::Burst {
+                count: 3,
+                interval: 0.1,
+            },
+        );
+        let mut shots = usize::from(weapon.tick(DT, pressed(), 1.0, true).is_some());
+        for _ in 0..20 {
+            shots += usize::from(weapon.tick(DT, Trigger::default(), 1.0, true).is_some());
+        }
+        assert_eq!(shots, 3);
+        assert!(!weapon.is_firing_sequence());
+    }
+
+    #[test]
+    fn charge_scales_with_hold_time() {
+        let mode = FireMode::Charge {
+            max_time: 1.0,
+            max_damage_scale: 3.0,
+            max_speed_scale: 2.0,
+        };
+        let mut weapon = Weapon::new(ProjectileKind::Laser, mode);
+        for _ in 0..10 {
+            assert!(weapon.tick(DT, held(), 0.5, true).is_none());
+        }
+        let shot = weapon.tick(DT, released(), 0.5, true).unwrap();
+        assert!((shot.damage - 2.0).abs() < 1e-4);
+        assert!((shot.speed - 1.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn salvo_needs_lock() {
+        let mode = FireMode::Salvo {
+            count: 2,
+            interval: 0.1,
+        };
+        let mut weapon = Weapon::new(ProjectileKind::Missile, mode);
+        assert!(weapon.tick(DT, released(), 1.0, false).is_none());
+        assert!(weapon.tick(DT, released(), 1.0, true).is_some());
+        assert!(weapon.is_firing_sequence());
+    }
+
+    #[test]
+    fn alternating_group_rotates_through_weapons() {
+        let mut loadout = WeaponLoadout {
+            weapons: vec![
+                Weapon::new(ProjectileKind::Turret, FireMode::Automatic),
+                Weapon::new(ProjectileKind::Turret, FireMode::Automatic),
+            ],
+            groups: vec![WeaponGroup::alternating(FireAction::Primary, [0, 1])],
+        };
+        let mut input = FireInput::default();
+        input.set(FireAction::Primary, true);
+
+        let triggers = loadout.triggers(&input);
+        assert!(triggers[0].pressed && !triggers[1].pressed);
+        loadout.advance_groups(&[0]);
+        let triggers = loadout.triggers(&input);
+        assert!(!triggers[0].pressed && triggers[1].pressed);
+    }
+}
@@ -0,0 +1,114 @@
+//! Deployable proximity mines.
+//!
+//! A mine is a pooled projectile like any other — dropped by a
+//! `ProjectileKind::Mine` weapon with zero muzzle speed, `Explosive`, with
+//! a little `ProjectileHealth` — plus a `Mine` component that keeps its
+//! proximity fuse and contact trigger dead until an arming delay passes.
+//! Mines live far longer than shots and quietly fizzle at the end instead
+//! of detonating.
+//!
+//! A stealth mine is hidden from the player, and unknown to AI, until a
+//! ship of that side comes within `mine_detection_range`. AI steers around
+//! every hostile mine it knows about with `mine_avoidance`.
+
+use bevy::prelude::*;
+
+use super::{Projectile, WeaponSettings};
+use crate::common::Player;
+use crate::faction::{Faction, FactionRelations};
+
+#[derive(Component, Debug)]
+pub struct Mine {
+    /// Seconds until the mine arms.
+    pub arming: f32,
+    pub stealth: bool,
+}
+
+impl Mine {
+    pub fn is_armed(&self) -> bool {
+        self.arming <= 0.0
+    }
+}
+
+/// A mine as AI sees it when planning movement: position, owning faction,
+/// and whether it's stealthed.
+pub type MineContact = (Vec3, Faction, bool);
+
+/// Steering push, in `0..=1` per mine, away from every armed mine that
+/// could hurt a ship of `faction` at `position` and that it knows about —
+/// any visible mine, or a stealth mine within `detection_range`. The push
+/// from each mine grows linearly from zero at `avoid_radius` to one at
+/// its center.
+pub fn mine_avoidance(
+    position: Vec3,
+    faction: Faction,
+    relations: &FactionRelations,
+    mines: &[MineContact],
+    detection_range: f32,
+    avoid_radius: f32,
+) -> Vec3 {
+    mines
+        .iter()
+        .filter(|(_, owner, _)| relations.can_damage(*owner, faction))
+        .filter_map(|&(mine, _, stealth)| {
+            let offset = position - mine;
+            let distance = offset.length();
+            let known = !stealth || distance <= detection_range;
+            (known && distance < avoid_radius)
+                .then(|| offset.normalize_or_zero() * (1.0 - distance / avoid_radius))
+        })
+        .sum()
+}
+
+pub(super) fn arm_mines(time: Res<Time>, mut mines: Query<&mut Mine>) {
+    let dt = time.delta_secs();
+    for mut mine in &mut mines {
+        if !mine.is_armed() {
+            mine.arming -= dt;
+        }
+    }
+}
+
+/// Hides stealth mines hostile to the player, armed or not, until the
+/// player is within detection range.
+pub(super) fn reveal_stealth_mines(
+    settings: Res<WeaponSettings>,
+    relations: Res<FactionRelations>,
+    player: Query<(&Transform, &Faction), With<Player>>,
+    mut mines: Query<(&Transform, &Projectile, &Mine, &mut Visibility)>,
+) {
+    let player = player.single().ok();
+    for (transform, projectile, mine, mut visibility) in &mut mines {
+        if !mine.stealth {
+            continue;
+        }
+        let revealed = player.is_none_or(|(player_transform, faction)| {
+            !relations.is_hostile(*faction, projectile.faction)
+                || player_transform.translation.distance(transform.translation)
+                    <= settings.mine_detection_range
+        });
+        visibility.set_if_neq(if revealed {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn avoidance_ignores_friendly_and_unseen_stealth_mines() {
+        let relations = FactionRelations::default();
+        let mine = Vec3::new(0.0, 0.0, -5.0);
+        let push = |contact: MineContact| {
+            mine_avoidance(Vec3::ZERO, Faction::Raiders, &relations, &[contact], 3.0, 10.0)
+        };
+        let hostile = push((mine, Faction::Player, false));
+        assert!(hostile.z > 0.0 && (hostile.length() - 0.5).abs() < 1e-5);
+        assert_eq!(push((mine, Faction::Raiders, false)), Vec3::ZERO);
+        assert_eq!(push((mine, Faction::Player, true)), Vec3::ZERO);
+    }
+}
@@ -0,0 +1,1348 @@
+//! Turret bullets, homing missiles, and lasers.
+//!
+//! All three are the same thing mechanically: a physics projectile spawned
+//! with a `LinearVelocity` and a lifetime, differing only in speed, turning
+//! (missiles home, the others fly straight), damage, and visuals. Lasers are
+//! not hitscan — they're just very fast — so travel time only becomes
+//! gameplay-relevant (dodgeable) at long range.
+//!
+//! What a ship fires, and how, is data: its `loadout::WeaponLoadout` binds
+//! weapons with a `FireMode` (automatic, burst, charge, lock-on salvo, …)
+//! to fire actions, and one generic `fire_weapons` system drives them all
+//! from each ship's `FireInput`. The player's mouse and the X key write
+//! that input (left = primary, right = secondary, middle = tertiary, X =
+//! utility); AI can write it just the same. Lock-on weapons need a lock
+//! first: hold their action to acquire, release to launch. Hit detection
+//! uses avian3d sensor collisions instead of a per-frame distance scan.
+//!
+//! Missiles are also `Explosive`: rather than damaging only the collider they
+//! touch, they detonate (on contact, on their proximity fuse, or when their
+//! lifetime runs out) and hurt every `Ship` in the blast radius, found with a
+//! `SpatialQuery` shape intersection. A missile whose target is destroyed
+//! can reacquire the nearest hostile in its seeker cone, and missiles carry
+//! a little `ProjectileHealth`, so enough turret fire shoots them down —
+//! which is what `point_defense` turrets do on their own. Proximity `mines`
+//! are the same machinery again, dropped rather than launched.
+//!
+//! Projectile entities are recycled through `pool::ProjectilePool` rather
+//! than spawned per shot; "despawning" a projectile below always means
+//! releasing it back to its pool.
+
+mod loadout;
+mod mines;
+mod point_defense;
+mod pool;
+
+pub use loadout::{FireAction, FireInput, FireMode, Trigger, Weapon, WeaponGroup, WeaponLoadout};
+pub use mines::{Mine, MineContact, mine_avoidance};
+pub use point_defense::{PointDefense, intercept_point};
+pub use pool::{ProjectileKind, ProjectilePool};
+
+use bevy::ecs::query::QueryData;
+use bevy::ecs::system::SystemParam;
+use bevy::platform::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+
+use avian3d::prelude::{
+    Collider, ColliderDisabled, CollisionStart, LinearVelocity, PhysicsSystems,
+    RigidBodyDisabled, Rotation, SpatialQuery, SpatialQueryFilter,
+};
+
+use crate::ai::AiSet;
+use crate::combat::{CombatSettings, ShipDamaged, ShipDestroyed, Ship, Staggered};
+use crate::common::Player;
+use crate::damage::{Damage, DamageType, Resistances};
+use crate::faction::{Faction, FactionRelations};
+use crate::shields::Shield;
+use crate::spatial::{SpatialIndex, SpatialPlugin};
+use crate::subsystems::{SubsystemKind, SubsystemTarget, Subsystems, subsystem_efficiency};
+use crate::vfx::{HitFlash, VfxSettings};
+
+pub struct WeaponsPlugin;
+
+impl Plugin for WeaponsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<SpatialPlugin>() {
+            app.add_plugins(SpatialPlugin);
+        }
+        app.init_resource::<WeaponSettings>()
+            .init_resource::<ProjectilePool>()
+            .add_message::<Detonation>()
+            .add_systems(
+                Startup,
+                (setup_weapon_assets, pool::fill_projectile_pool).chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    // AI ships set their triggers in `AiSet::Actions`.
+                    (
+                        read_player_fire_input,
+                        acquire_missile_locks,
+                        fire_weapons.after(AiSet::Actions),
+                    )
+                        .chain(),
+                    point_defense::operate_point_defense,
+                    (mines::arm_mines, mines::reveal_stealth_mines).chain(),
+                    (retarget_missiles, update_missile_warnings).chain(),
+                    (
+                        tick_projectile_lifetime,
+                        trigger_proximity_fuses,
+                        intercept_projectiles,
+                        handle_projectile_hits,
+                        resolve_detonations,
+                    )
+                        .chain()
+                        .in_set(HitDetectionSet),
+                    pool::report_pool_pressure.after(HitDetectionSet),
+                ),
+            )
+            .add_systems(
+                FixedPostUpdate,
+                steer_missiles.in_set(PhysicsSystems::Prepare),
+            );
+    }
+}
+
+/// Runs after a frame's projectile hits have been resolved. `AiSet::Actions`
+/// is ordered `.after(HitDetectionSet)` so a `Staggered` ship never gets one stray
+/// frame of AI control before the exclusion takes effect.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HitDetectionSet;
+
+// ── Resources ────────────────────────────────────────────────────────────────
+
+#[derive(Resource)]
+pub struct WeaponSettings {
+    pub turret_speed: f32,
+    pub turret_damage: Damage,
+    pub turret_cooldown: f32,
+    /// Used with the projectile's current velocity to compute knockback
+    /// impulse on hit: `impulse = velocity * mass`.
+    pub turret_mass: f32,
+
+    pub laser_speed: f32,
+    pub laser_damage: Damage,
+    pub laser_cooldown: f32,
+    pub laser_mass: f32,
+
+    pub missile_speed: f32,
+    pub missile_turn_rate: f32,
+    pub missile_damage: Damage,
+    pub missile_cooldown: f32,
+    pub missile_lock_range: f32,
+    pub missile_mass: f32,
+    /// Half-angle, in radians, of the lock-on cone in front of the ship.
+    pub missile_lock_cone: f32,
+    /// Seconds the missile trigger must be held on one target, inside the
+    /// lock cone, before releasing it launches a missile.
+    pub missile_lock_time: f32,
+    /// Every `Ship` within this distance of a detonation takes damage.
+    pub missile_blast_radius: f32,
+    /// Distance to a ship at which a missile detonates without touching it.
+    pub missile_proximity_fuse: f32,
+    /// Knockback impulse at the center of the blast.
+    pub missile_blast_impulse: f32,
+    /// Fraction of full damage and impulse left at the blast's edge; both
+    /// fall off linearly from the center out to it.
+    pub missile_blast_edge_falloff: f32,
+    /// Whether colliders between the blast and a ship shield it.
+    pub missile_blast_occlusion: bool,
+    /// Whether a missile whose target is gone looks for a new one instead
+    /// of flying on ballistic.
+    pub missile_retarget: bool,
+    /// Half-angle, in radians, of the cone a missile searches when
+    /// retargeting, around its direction of flight.
+    pub missile_seeker_cone: f32,
+    pub missile_seeker_range: f32,
+    /// Damage a missile absorbs before being shot down.
+    pub missile_health: f32,
+
+    pub mine_damage: Damage,
+    pub mine_cooldown: f32,
+    /// Seconds after being dropped before a mine can go off.
+    pub mine_arming_time: f32,
+    /// Distance at which an armed mine detonates under a hostile ship.
+    pub mine_trigger_radius: f32,
+    pub mine_blast_radius: f32,
+    pub mine_blast_impulse: f32,
+    pub mine_health: f32,
+    /// Mines fizzle out after this long; they don't use
+    /// `projectile_lifetime`.
+    pub mine_lifetime: f32,
+    /// Whether dropped mines are stealthed; see `mines`.
+    pub mine_stealth: bool,
+    /// Distance within which a ship spots a stealth mine.
+    pub mine_detection_range: f32,
+    /// AI starts steering away from known hostile mines inside this range.
+    pub mine_avoid_radius: f32,
+
+    /// Shared despawn timer for every other projectile kind; this is what
+    /// caps each weapon's effective range (speed * lifetime).
+    pub projectile_lifetime: f32,
+
+    /// Entities pre-allocated per projectile kind at startup. A pool that
+    /// runs dry grows (see `weapons.pool.grown`), so these only need to
+    /// cover typical peak load.
+    pub turret_pool_size: usize,
+    pub laser_pool_size: usize,
+    pub missile_pool_size: usize,
+    pub mine_pool_size: usize,
+}
+
+impl Default for WeaponSettings {
+    fn default() -> Self {
+        Self {
+            turret_speed: 120.0,
+            turret_damage: Damage::new(5.0, DamageType::Kinetic),
+            turret_cooldown: 0.1,
+            turret_mass: 0.2,
+
+            laser_speed: 600.0,
+            laser_damage: Damage::new(15.0, DamageType::Energy),
+            laser_cooldown: 0.6,
+            laser_mass: 0.1,
+
+            missile_speed: 40.0,
+            missile_turn_rate: 2.0,
+            missile_damage: Damage::new(40.0, DamageType::Explosive),
+            missile_cooldown: 1.5,
+            missile_lock_range: 150.0,
+            missile_mass: 3.0,
+            missile_lock_cone: 30f32.to_radians(),
+            missile_lock_time: 1.0,
+            missile_blast_radius: 8.0,
+            missile_proximity_fuse: 2.5,
+            missile_blast_impulse: 120.0,
+            missile_blast_edge_falloff: 0.2,
+            missile_blast_occlusion: true,
+            missile_retarget: true,
+            missile_seeker_cone: 25f32.to_radians(),
+            missile_seeker_range: 80.0,
+            missile_health: 10.0,
+
+            mine_damage: Damage::new(60.0, DamageType::Explosive),
+            mine_cooldown: 2.0,
+            mine_arming_time: 2.0,
+            mine_trigger_radius: 6.0,
+            mine_blast_radius: 10.0,
+            mine_blast_impulse: 150.0,
+            mine_health: 20.0,
+            mine_lifetime: 90.0,
+            mine_stealth: false,
+            mine_detection_range: 15.0,
+            mine_avoid_radius: 18.0,
+
+            projectile_lifetime: 6.0,
+
+            turret_pool_size: 256,
+            laser_pool_size: 32,
+            missile_pool_size: 16,
+            mine_pool_size: 24,
+        }
+    }
+}
+
+/// The `WeaponSettings` that apply to one projectile kind.
+#[derive(Clone, Copy, Debug)]
+pub struct WeaponStats {
+    pub speed: f32,
+    pub damage: Damage,
+    pub cooldown: f32,
+    pub mass: f32,
+    pub lifetime: f32,
+}
+
+impl WeaponSettings {
+    pub fn stats(&self, kind: ProjectileKind) -> WeaponStats {
+        match kind {
+            ProjectileKind::Turret => WeaponStats {
+                speed: self.turret_speed,
+                damage: self.turret_damage,
+                cooldown: self.turret_cooldown,
+                mass: self.turret_mass,
+                lifetime: self.projectile_lifetime,
+            },
+            ProjectileKind::Laser => WeaponStats {
+                speed: self.laser_speed,
+                damage: self.laser_damage,
+                cooldown: self.laser_cooldown,
+                mass: self.laser_mass,
+                lifetime: self.projectile_lifetime,
+            },
+            ProjectileKind::Missile => WeaponStats {
+                speed: self.missile_speed,
+                damage: self.missile_damage,
+                cooldown: self.missile_cooldown,
+                mass: self.missile_mass,
+                lifetime: self.projectile_lifetime,
+            },
+            // Mines are dropped, not fired, and only ever do blast damage.
+            ProjectileKind::Mine => WeaponStats {
+                speed: 0.0,
+                damage: self.mine_damage,
+                cooldown: self.mine_cooldown,
+                mass: 0.0,
+                lifetime: self.mine_lifetime,
+            },
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub(crate) struct WeaponAssets {
+    turret_mesh: Handle<Mesh>,
+    turret_material: Handle<StandardMaterial>,
+    laser_mesh: Handle<Mesh>,
+    laser_material: Handle<StandardMaterial>,
+    missile_mesh: Handle<Mesh>,
+    missile_material: Handle<StandardMaterial>,
+    mine_mesh: Handle<Mesh>,
+    mine_material: Handle<StandardMaterial>,
+}
+
+// ── Components ───────────────────────────────────────────────────────────────
+
+/// Missile seeker state, built up while the missile trigger is held.
+/// Progress only accumulates while the same target stays inside the lock
+/// cone; losing it (or it despawning) starts acquisition over.
+#[derive(Component, Default)]
+pub struct MissileLock {
+    pub target: Option<Entity>,
+    /// Seconds the current target has been held in the cone.
+    pub progress: f32,
+}
+
+impl MissileLock {
+    /// Acquisition progress in `0..=1`, for the HUD.
+    pub fn fraction(&self, lock_time: f32) -> f32 {
+        if self.target.is_none() {
+            return 0.0;
+        }
+        (self.progress / lock_time.max(1e-3)).min(1.0)
+    }
+
+    pub fn is_locked(&self, lock_time: f32) -> bool {
+        self.target.is_some() && self.progress >= lock_time
+    }
+
+    fn reset(&mut self) {
+        self.target = None;
+        self.progress = 0.0;
+    }
+}
+
+/// Present on any entity currently targeted by at least one `Homing`
+/// projectile; maintained by `update_missile_warnings`. The HUD reads it for
+/// the player, and AI can read it to react to inbound fire.
+#[derive(Component, Debug)]
+pub struct MissileWarning {
+    pub inbound: u32,
+    /// Distance to the closest inbound missile.
+    pub closest: f32,
+}
+
+#[derive(Component)]
+pub struct Projectile {
+    pub damage: Damage,
+    pub owner: Entity,
+    /// The owner's faction at the time of firing, so damage filtering still
+    /// works after the owner is gone.
+    pub faction: Faction,
+    pub mass: f32,
+    /// Subsystem the shooter was aiming for, if any; see `subsystems`.
+    pub subsystem: Option<SubsystemKind>,
+}
+
+#[derive(Component)]
+struct ProjectileLifetime(Timer);
+
+/// Turns a projectile into area damage: see `resolve_detonations`. The
+/// projectile's own `Projectile::damage` is what lands at the blast center.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Explosive {
+    pub radius: f32,
+    /// Proximity fuse distance; zero disables it.
+    pub proximity: f32,
+    pub impulse: f32,
+    pub edge_falloff: f32,
+    pub occlusion: bool,
+}
+
+/// Written whenever an `Explosive` projectile goes off, whatever triggered
+/// it. The projectile itself is already back in its pool by the time this
+/// is read.
+#[derive(Message, Clone, Copy, Debug)]
+pub struct Detonation {
+    pub projectile: Entity,
+    pub position: Vec3,
+    pub owner: Entity,
+    pub faction: Faction,
+    pub damage: Damage,
+    pub explosive: Explosive,
+}
+
+impl Detonation {
+    fn new(entity: Entity, position: Vec3, projectile: &Projectile, explosive: &Explosive) -> Self {
+        Self {
+            projectile: entity,
+            position,
+            owner: projectile.owner,
+            faction: projectile.faction,
+            damage: projectile.damage,
+            explosive: *explosive,
+        }
+    }
+}
+
+/// Hit points of a projectile that can be shot down. Any hostile
+/// projectile without health of its own that touches it deals its
+/// `Projectile::damage` (unmodified by resistances) and is spent.
+#[derive(Component, Debug)]
+pub struct ProjectileHealth(pub f32);
+
+#[derive(Component)]
+pub struct Homing {
+    pub target: Entity,
+    pub turn_rate: f32,
+    pub speed: f32,
+    /// Whether to reacquire when `target` is gone; see `retarget_missiles`.
+    pub retarget: bool,
+    /// Half-angle and range of the cone searched when retargeting.
+    pub seeker_cone: f32,
+    pub seeker_range: f32,
+}
+
+// ── Startup ──────────────────────────────────────────────────────────────────
+
+fn setup_weapon_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(WeaponAssets {
+        turret_mesh: meshes.add(Cuboid::new(0.08, 0.08, 1.5)),
+        turret_material: materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            unlit: true,
+            ..default()
+        }),
+        laser_mesh: meshes.add(Cuboid::new(0.05, 0.05, 6.0)),
+        laser_material: materials.add(StandardMaterial {
+            base_color: Color::srgb(0.2, 1.0, 1.0),
+            unlit: true,
+            ..default()
+        }),
+        missile_mesh: meshes.add(Cylinder::new(0.1, 0.8)),
+        missile_material: materials.add(StandardMaterial {
+            base_color: Color::srgb(1.0, 0.55, 0.1),
+            unlit: true,
+            ..default()
+        }),
+        mine_mesh: meshes.add(Sphere::new(0.5).mesh().ico(1).unwrap()),
+        mine_material: materials.add(StandardMaterial {
+            base_color: Color::srgb(0.9, 0.1, 0.3),
+            emissive: LinearRgba::rgb(0.6, 0.0, 0.1),
+            ..default()
+        }),
+    });
+}
+
+// ── Firing ───────────────────────────────────────────────────────────────────
+
+/// Takes a projectile of `kind` from the pool and puts it in flight.
+#[allow(clippy::too_many_arguments)]
+fn spawn_projectile(
+    commands: &mut Commands,
+    pool: &mut ProjectilePool,
+    assets: &WeaponAssets,
+    kind: ProjectileKind,
+    origin: Vec3,
+    rotation: Quat,
+    velocity: Vec3,
+    projectile: Projectile,
+    lifetime: f32,
+) -> Entity {
+    let entity = pool.acquire(commands, assets, kind);
+    commands
+        .entity(entity)
+        .remove::<(RigidBodyDisabled, ColliderDisabled)>()
+        .insert((
+            Transform::from_translation(origin).with_rotation(rotation),
+            Visibility::Visible,
+            LinearVelocity(velocity),
+            projectile,
+            ProjectileLifetime(Timer::from_seconds(lifetime, TimerMode::Once)),
+        ));
+    entity
+}
+
+/// The firing ship, as seen by `acquire_missile_locks` and `fire_weapons`.
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub(crate) struct Shooter {
+    entity: Entity,
+    transform: &'static Transform,
+    faction: &'static Faction,
+    loadout: &'static mut WeaponLoadout,
+    subsystems: Option<&'static Subsystems>,
+    subsystem_target: Option<&'static SubsystemTarget>,
+}
+
+impl ShooterItem<'_, '_> {
+    fn projectile(&self, damage: Damage, mass: f32) -> Projectile {
+        Projectile {
+            damage,
+            owner: self.entity,
+            faction: *self.faction,
+            mass,
+            subsystem: self.subsystem_target.and_then(|target| target.0),
+        }
+    }
+
+    /// `base` stretched by damage to the weapon mounts.
+    fn cooldown(&self, base: f32) -> f32 {
+        base / subsystem_efficiency(self.subsystems, SubsystemKind::Weapons)
+    }
+}
+
+/// Distance to `target` if it sits inside the lock cone, `None` otherwise.
+fn lock_cone_distance(
+    origin: Vec3,
+    forward: Vec3,
+    target: Vec3,
+    range: f32,
+    cone: f32,
+) -> Option<f32> {
+    let offset = target - origin;
+    let distance = offset.length();
+    if distance < 1e-3 || distance > range {
+        return None;
+    }
+    let angle = forward.angle_between(offset / distance);
+    (angle <= cone).then_some(distance)
+}
+
+/// Mouse buttons drive the player's fire actions: left = primary, right =
+/// secondary, middle = tertiary. The keyboard is committed to flight in
+/// `controller`, bar X for the utility action.
+fn read_player_fire_input(
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut player: Query<&mut FireInput, With<Player>>,
+) {
+    let Ok(mut input) = player.single_mut() else {
+        return;
+    };
+    input.set(FireAction::Primary, mouse.pressed(MouseButton::Left));
+    input.set(FireAction::Secondary, mouse.pressed(MouseButton::Right));
+    input.set(FireAction::Tertiary, mouse.pressed(MouseButton::Middle));
+    input.set(FireAction::Utility, keys.pressed(KeyCode::KeyX));
+}
+
+/// While a ship holds the action of a lock-on weapon, builds a lock on the
+/// closest hostile in the cone. The current target is kept for as long as
+/// it stays in the cone, even if something closer drifts in, so
+/// acquisition doesn't restart on every near miss.
+fn acquire_missile_locks(
+    time: Res<Time>,
+    settings: Res<WeaponSettings>,
+    relations: Res<FactionRelations>,
+    index: Res<SpatialIndex>,
+    mut shooters: Query<(Shooter, &FireInput, &mut MissileLock)>,
+    targets: Query<(Entity, &Transform, &Faction)>,
+) {
+    for (shooter, input, mut lock) in &mut shooters {
+        if !shooter.loadout.lock_held(input) {
+            continue;
+        }
+
+        let transform = shooter.transform;
+        let faction = *shooter.faction;
+        let forward = *transform.forward();
+        let lock_range = settings.missile_lock_range
+            * subsystem_efficiency(shooter.subsystems, SubsystemKind::Sensors);
+        let in_cone = |(target, target_transform, target_faction): (Entity, &Transform, &Faction)| {
+            if target == shooter.entity || !relations.is_hostile(faction, *target_faction) {
+                return None;
+            }
+            lock_cone_distance(
+                transform.translation,
+                forward,
+                target_transform.translation,
+                lock_range,
+                settings.missile_lock_cone,
+            )
+        };
+
+        let held = lock
+            .target
+            .and_then(|target| targets.get(target).ok())
+            .and_then(in_cone);
+        if held.is_some() {
+            lock.progress += time.delta_secs();
+            continue;
+        }
+
+        let candidate = index
+            .in_cone(transform.translation, forward, settings.missile_lock_cone, lock_range)
+            .filter(|(entry, _)| {
+                entry.entity != shooter.entity && relations.is_hostile(faction, entry.faction)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entry, _)| entry.entity);
+
+        if lock.target.is_some() && candidate.is_none() {
+            debug!("Missile lock lost");
+        }
+        lock.target = candidate;
+        lock.progress = 0.0;
+    }
+}
+
+/// Ticks every weapon of every ship under its group triggers and spawns
+/// whatever fires. Releasing a lock-on weapon's action consumes the
+/// ship's `MissileLock`: a full lock becomes the salvo's homing target,
+/// anything less fires nothing. A full lock released while the launcher
+/// is still cycling is kept for the next release.
+pub(crate) fn fire_weapons(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<WeaponSettings>,
+    assets: Res<WeaponAssets>,
+    mut pool: ResMut<ProjectilePool>,
+    mut shooters: Query<(Shooter, &FireInput, Option<&mut MissileLock>)>,
+) {
+    let dt = time.delta_secs();
+    for (mut shooter, input, mut lock) in &mut shooters {
+        let triggers = shooter.loadout.triggers(input);
+        let locked_target = lock
+            .as_ref()
+            .filter(|lock| lock.is_locked(settings.missile_lock_time))
+            .and_then(|lock| lock.target);
+        let mut lock_spent = false;
+        let mut fired = Vec::new();
+
+        for (index, trigger) in triggers.into_iter().enumerate() {
+            let weapon = &shooter.loadout.weapons[index];
+            let stats = settings.stats(weapon.kind);
+            let cooldown = shooter.cooldown(stats.cooldown);
+            let salvo = matches!(weapon.mode, FireMode::Salvo { .. });
+            let starts_salvo = salvo && trigger.just_released && !weapon.is_firing_sequence();
+            if starts_salvo && locked_target.is_none() {
+                info!("Missile fire: no lock");
+                lock_spent = true;
+            }
+
+            let weapon = &mut shooter.loadout.weapons[index];
+            if starts_salvo {
+                weapon.target = locked_target;
+            }
+            let armed = !salvo || weapon.target.is_some();
+            let Some(scale) = weapon.tick(dt, trigger, cooldown, armed) else {
+                continue;
+            };
+            lock_spent |= starts_salvo;
+            let (kind, offset, target) = (weapon.kind, weapon.offset, weapon.target);
+            fired.push(index);
+
+            let transform = shooter.transform;
+            let forward = *transform.forward();
+            let speed = stats.speed * scale.speed;
+            let id = spawn_projectile(
+                &mut commands,
+                &mut pool,
+                &assets,
+                kind,
+                transform.translation + transform.rotation * offset,
+                transform.rotation,
+                forward * speed,
+                shooter.projectile(stats.damage.scaled(scale.damage), stats.mass),
+                stats.lifetime,
+            );
+            if kind == ProjectileKind::Mine {
+                commands.entity(id).insert((
+                    Mine {
+                        arming: settings.mine_arming_time,
+                        stealth: settings.mine_stealth,
+                    },
+                    ProjectileHealth(settings.mine_health),
+                    Explosive {
+                        radius: settings.mine_blast_radius,
+                        proximity: settings.mine_trigger_radius,
+                        impulse: settings.mine_blast_impulse,
+                        edge_falloff: settings.missile_blast_edge_falloff,
+                        occlusion: settings.missile_blast_occlusion,
+                    },
+                ));
+                // Dropped dark; `reveal_stealth_mines` shows it to anyone
+                // who should see it from the next frame on.
+                if settings.mine_stealth {
+                    commands.entity(id).insert(Visibility::Hidden);
+                }
+            } else if let (ProjectileKind::Missile, Some(target)) = (kind, target) {
+                commands.entity(id).insert((
+                    Homing {
+                        target,
+                        turn_rate: settings.missile_turn_rate,
+                        speed,
+                        retarget: settings.missile_retarget,
+                        seeker_cone: settings.missile_seeker_cone,
+                        seeker_range: settings.missile_seeker_range,
+                    },
+                    ProjectileHealth(settings.missile_health),
+                    Explosive {
+                        radius: settings.missile_blast_radius,
+                        proximity: settings.missile_proximity_fuse,
+                        impulse: settings.missile_blast_impulse,
+                        edge_falloff: settings.missile_blast_edge_falloff,
+                        occlusion: settings.missile_blast_occlusion,
+                    },
+                ));
+            }
+        }
+
+        shooter.loadout.advance_groups(&fired);
+        if lock_spent && let Some(lock) = lock.as_mut() {
+            lock.reset();
+        }
+    }
+}
+
+/// Points missiles whose target is gone at the closest hostile ship inside
+/// their seeker cone. One that finds nothing flies on ballistic and keeps
+/// looking every frame until its lifetime runs out.
+fn retarget_missiles(
+    relations: Res<FactionRelations>,
+    index: Res<SpatialIndex>,
+    mut missiles: Query<(&Transform, &LinearVelocity, &Projectile, &mut Homing)>,
+    targets: Query<(), With<Ship>>,
+) {
+    for (transform, linvel, projectile, mut homing) in &mut missiles {
+        if !homing.retarget || targets.contains(homing.target) {
+            continue;
+        }
+        let origin = transform.translation;
+        let forward = linvel.0.try_normalize().unwrap_or(*transform.forward());
+        // Searches outwards from the missile, so the first ring with a
+        // hostile in the cone ends it.
+        let candidate = index
+            .nearest(origin, 1, homing.seeker_range, |entry| {
+                entry.entity != projectile.owner
+                    && targets.contains(entry.entity)
+                    && relations.is_hostile(projectile.faction, entry.faction)
+                    && lock_cone_distance(origin, forward, entry.position, homing.seeker_range, homing.seeker_cone)
+                        .is_some()
+            })
+            .first()
+            .map(|(entry, _)| entry.entity);
+        if let Some(target) = candidate {
+            debug!("Missile retargeted to {target}");
+            homing.target = target;
+        }
+    }
+}
+
+/// Keeps `MissileWarning` in sync with the set of live `Homing` targets.
+fn update_missile_warnings(
+    mut commands: Commands,
+    missiles: Query<(&Transform, &Homing)>,
+    positions: Query<&Transform, Without<Homing>>,
+    mut warned: Query<(Entity, &mut MissileWarning)>,
+) {
+    let mut inbound: HashMap<Entity, (u32, f32)> = HashMap::new();
+    for (missile_transform, homing) in &missiles {
+        let Ok(target_transform) = positions.get(homing.target) else {
+            continue;
+        };
+        let distance = missile_transform
+            .translation
+            .distance(target_transform.translation);
+        let entry = inbound.entry(homing.target).or_insert((0, f32::MAX));
+        entry.0 += 1;
+        entry.1 = entry.1.min(distance);
+    }
+
+    for (entity, mut warning) in &mut warned {
+        match inbound.remove(&entity) {
+            Some((count, closest)) => {
+                warning.inbound = count;
+                warning.closest = closest;
+            }
+            None => {
+                commands.entity(entity).remove::<MissileWarning>();
+            }
+        }
+    }
+
+    for (entity, (inbound, closest)) in inbound {
+        commands
+            .entity(entity)
+            .try_insert(MissileWarning { inbound, closest });
+    }
+}
+
+// ── Flight ───────────────────────────────────────────────────────────────────
+
+fn steer_missiles(
+    time: Res<Time<Fixed>>,
+    targets: Query<&Transform>,
+    mut missiles: Query<(&Transform, &Homing, &mut LinearVelocity, &mut Rotation)>,
+) {
+    let dt = time.delta_secs();
+    for (transform, homing, mut linvel, mut rotation) in &mut missiles {
+        let Ok(target_transform) = targets.get(homing.target) else {
+            // Target despawned — fly on ballistic until `retarget_missiles`
+            // finds a new one or the lifetime timer cleans this up.
+            continue;
+        };
+
+        let to_target = (target_transform.translation - transform.translation).normalize_or_zero();
+        if to_target == Vec3::ZERO {
+            continue;
+        }
+
+        let current_dir = {
+            let d = linvel.0.normalize_or_zero();
+            if d == Vec3::ZERO { *transform.forward() } else { d }
+        };
+
+        let rot_current = Quat::from_rotation_arc(Vec3::NEG_Z, current_dir);
+        let rot_target = Quat::from_rotation_arc(Vec3::NEG_Z, to_target);
+        let t = (homing.turn_rate * dt).min(1.0);
+        let new_rot = rot_current.slerp(rot_target, t);
+        let new_dir = new_rot * Vec3::NEG_Z;
+
+        linvel.0 = new_dir * homing.speed;
+        rotation.0 = new_rot;
+    }
+}
+
+/// Expired explosives self-destruct, except mines, which fizzle.
+#[allow(clippy::type_complexity)]
+fn tick_projectile_lifetime(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    time: Res<Time>,
+    mut detonations: MessageWriter<Detonation>,
+    mut query: Query<(
+        Entity,
+        &mut ProjectileLifetime,
+        &Transform,
+        &Projectile,
+        Option<&Explosive>,
+        Has<Mine>,
+    )>,
+) {
+    for (entity, mut lifetime, transform, projectile, explosive, is_mine) in &mut query {
+        if lifetime.0.tick(time.delta()).is_finished() {
+            if let Some(explosive) = explosive
+                && !is_mine
+            {
+                detonations.write(Detonation::new(
+                    entity,
+                    transform.translation,
+                    projectile,
+                    explosive,
+                ));
+            }
+            pool.release(&mut commands, entity);
+        }
+    }
+}
+
+fn trigger_proximity_fuses(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    spatial: SpatialQuery,
+    mut detonations: MessageWriter<Detonation>,
+    relations: Res<FactionRelations>,
+    explosives: Query<(Entity, &Transform, &Projectile, &Explosive, Option<&Mine>)>,
+    ships: Query<Option<&Faction>, With<Ship>>,
+) {
+    for (entity, transform, projectile, explosive, mine) in &explosives {
+        if explosive.proximity <= 0.0 || mine.is_some_and(|mine| !mine.is_armed()) {
+            continue;
+        }
+        let filter = SpatialQueryFilter::from_excluded_entities([entity, projectile.owner]);
+        let triggered = spatial
+            .shape_intersections(
+                &Collider::sphere(explosive.proximity),
+                transform.translation,
+                Quat::IDENTITY,
+                &filter,
+            )
+            .into_iter()
+            .any(|hit| {
+                ships.get(hit).is_ok_and(|faction| {
+                    relations.is_hostile(projectile.faction, faction.copied().unwrap_or_default())
+                })
+            });
+        if triggered {
+            detonations.write(Detonation::new(
+                entity,
+                transform.translation,
+                projectile,
+                explosive,
+            ));
+            pool.release(&mut commands, entity);
+        }
+    }
+}
+
+// ── Hit detection ────────────────────────────────────────────────────────────
+
+/// A ship that projectiles and blasts can damage. A ship with no `Faction`
+/// counts as `Faction::Neutral`; one with no `Resistances` uses the
+/// baseline profile.
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct DamageableShip {
+    ship: &'static mut Ship,
+    linvel: &'static mut LinearVelocity,
+    transform: &'static Transform,
+    collider: Option<&'static Collider>,
+    faction: Option<&'static Faction>,
+    shield: Option<&'static mut Shield>,
+    resistances: Option<&'static Resistances>,
+    subsystems: Option<&'static mut Subsystems>,
+}
+
+impl DamageableShipItem<'_, '_> {
+    fn faction(&self) -> Faction {
+        self.faction.copied().unwrap_or_default()
+    }
+}
+
+type DamageableShips<'w, 's> = Query<'w, 's, DamageableShip, Without<Projectile>>;
+
+/// One instance of damage landing on a `Ship`.
+struct ShipHit {
+    damage: Damage,
+    /// World-space direction from the ship towards where the hit came from,
+    /// used to pick a shield facing.
+    source_direction: Vec3,
+    /// Knockback impulse, divided by the ship's mass on application.
+    impulse: Vec3,
+    /// Subsystem to spill hull damage into; random if `None`.
+    subsystem: Option<SubsystemKind>,
+    /// Ship that fired the projectile.
+    attacker: Entity,
+}
+
+/// Everything needed to land a `ShipHit`, shared by direct projectile hits
+/// and blast damage.
+#[derive(SystemParam)]
+struct HitResolver<'w, 's> {
+    commands: Commands<'w, 's>,
+    destroyed: MessageWriter<'w, ShipDestroyed>,
+    damaged: MessageWriter<'w, ShipDamaged>,
+    combat_settings: Res<'w, CombatSettings>,
+    vfx_settings: Res<'w, VfxSettings>,
+}
+
+impl HitResolver<'_, '_> {
+    /// Runs the hit through the ship's shield (if any), scaled by the
+    /// ship's resistances at each layer, reports it as a `ShipDamaged`,
+    /// then despawns the ship with a
+    /// `ShipDestroyed` if what reached the hull was lethal. A surviving
+    /// ship is knocked back, and staggered and flashed only if the hull
+    /// actually took damage. A ship already at zero health (hit twice in
+    /// one frame) is left alone.
+    fn apply(&mut self, entity: Entity, target: DamageableShipItem, hit: ShipHit) {
+        let DamageableShipItem {
+            mut ship,
+            mut linvel,
+            transform,
+            shield,
+            resistances,
+            subsystems,
+            ..
+        } = target;
+        if ship.health <= 0.0 {
+            return;
+        }
+        let resistances = resistances.cloned().unwrap_or_default();
+        let through = match shield {
+            Some(mut shield) => {
+                let facing = shield.facing_for(transform.rotation, hit.source_direction);
+                let amount = hit.damage.amount * resistances.shield_multiplier(hit.damage.kind);
+                shield.absorb(facing, amount)
+            }
+            None => 1.0,
+        };
+        let hull_damage =
+            hit.damage.amount * through * resistances.hull_multiplier(hit.damage.kind);
+        ship.health -= hull_damage;
+        self.damaged.write(ShipDamaged {
+            ship: entity,
+            attacker: hit.attacker,
+            amount: hit.damage.amount,
+            hull_damage,
+        });
+        if let Some(mut subsystems) = subsystems
+            && hull_damage > 0.0
+        {
+            let kind = hit.subsystem.unwrap_or_else(SubsystemKind::random);
+            subsystems.take_hull_damage(kind, hull_damage);
+        }
+
+        if ship.health <= 0.0 {
+            self.destroyed.write(ShipDestroyed {
+                position: transform.translation,
+            });
+            self.commands.entity(entity).despawn();
+        } else {
+            linvel.0 += hit.impulse / ship.mass;
+            if hull_damage <= 0.0 {
+                return;
+            }
+            self.commands
+                .entity(entity)
+                .insert(Staggered::new(
+                    self.combat_settings.stagger_duration,
+                    self.combat_settings.stagger_angular_kick,
+                ))
+                .insert(HitFlash::new(self.vfx_settings.flash_duration));
+        }
+    }
+}
+
+/// Lets projectiles shoot down hostile projectiles that have
+/// `ProjectileHealth`. Both sides of the contact are resolved here: the
+/// shot is spent, and the target goes back to its pool — without
+/// detonating — once its health runs out.
+fn intercept_projectiles(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    mut events: MessageReader<CollisionStart>,
+    relations: Res<FactionRelations>,
+    shots: Query<&Projectile, Without<ProjectileHealth>>,
+    mut shootable: Query<(&Projectile, &mut ProjectileHealth)>,
+) {
+    let mut spent = HashSet::new();
+    for event in events.read() {
+        let (a, b) = (event.collider1, event.collider2);
+        let (shot_entity, target_entity) = if shots.contains(a) && shootable.contains(b) {
+            (a, b)
+        } else if shots.contains(b) && shootable.contains(a) {
+            (b, a)
+        } else {
+            continue;
+        };
+        let (Ok(shot), Ok((target, mut health))) =
+            (shots.get(shot_entity), shootable.get_mut(target_entity))
+        else {
+            continue;
+        };
+        if health.0 <= 0.0
+            || shot.owner == target.owner
+            || !relations.can_damage(shot.faction, target.faction)
+            || !spent.insert(shot_entity)
+        {
+            continue;
+        }
+
+        health.0 -= shot.damage.amount;
+        pool.release(&mut commands, shot_entity);
+        if health.0 <= 0.0 {
+            debug!("Projectile {target_entity} shot down");
+            pool.release(&mut commands, target_entity);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_projectile_hits(
+    mut commands: Commands,
+    mut pool: ResMut<ProjectilePool>,
+    mut events: MessageReader<CollisionStart>,
+    mut detonations: MessageWriter<Detonation>,
+    mut hits: HitResolver,
+    projectiles: Query<(&Projectile, &LinearVelocity, &Transform, Option<&Explosive>)>,
+    mines: Query<&Mine>,
+    relations: Res<FactionRelations>,
+    mut ships: DamageableShips,
+) {
+    for event in events.read() {
+        let (a, b) = (event.collider1, event.collider2);
+
+        let (proj_entity, other_entity, (projectile, proj_velocity, proj_transform, explosive)) =
+            if let Ok(p) = projectiles.get(a) {
+                (a, b, p)
+            } else if let Ok(p) = projectiles.get(b) {
+                (b, a, p)
+            } else {
+                continue;
+            };
+
+        // Projectile-on-projectile contact is `intercept_projectiles`' job.
+        if other_entity == projectile.owner || projectiles.contains(other_entity) {
+            continue;
+        }
+        let victim = ships.get_mut(other_entity).ok();
+        // Mines are inert until armed and, like their proximity fuse, only
+        // go off against hostile ships; anything else just moves them.
+        if let Ok(mine) = mines.get(proj_entity)
+            && (!mine.is_armed()
+                || !victim.as_ref().is_some_and(|victim| {
+                    relations.is_hostile(projectile.faction, victim.faction())
+                }))
+        {
+            continue;
+        }
+        if let Some(victim) = &victim
+            && !relations.can_damage(projectile.faction, victim.faction())
+        {
+            // Allied ships are transparent to friendly fire: the shot
+            // carries on rather than being absorbed.
+            continue;
+        }
+
+        if let Some(explosive) = explosive {
+            detonations.write(Detonation::new(
+                proj_entity,
+                proj_transform.translation,
+                projectile,
+                explosive,
+            ));
+        } else if let Some(victim) = victim {
+            hits.apply(
+                other_entity,
+                victim,
+                ShipHit {
+                    damage: projectile.damage,
+                    source_direction: -proj_velocity.0.normalize_or_zero(),
+                    impulse: proj_velocity.0 * projectile.mass,
+                    subsystem: projectile.subsystem,
+                    attacker: projectile.owner,
+                },
+            );
+        }
+
+        pool.release(&mut commands, proj_entity);
+    }
+}
+
+/// Damage and impulse multiplier at `distance` from a blast center: 1 at
+/// the center, `edge` at `radius`, 0 beyond.
+fn blast_falloff(distance: f32, radius: f32, edge: f32) -> f32 {
+    if distance > radius || radius <= 0.0 {
+        return 0.0;
+    }
+    1.0 - (1.0 - edge) * (distance / radius)
+}
+
+/// The point of a ship's hull nearest to `point`, or its center when it has
+/// no collider. `point` itself when it's inside the hull.
+fn nearest_hull_point(collider: Option<&Collider>, transform: &Transform, point: Vec3) -> Vec3 {
+    match collider {
+        Some(collider) => collider.project_point(transform.translation, transform.rotation, point, true).0,
+        None => transform.translation,
+    }
+}
+
+/// Applies each detonation to every `Ship` overlapping its blast sphere.
+/// Falloff and occlusion are both measured to the nearest point of the
+/// hull, so a large ship caught by the rim takes edge damage even if its
+/// center is out of reach or behind cover. The same projectile can
+/// detonate twice in a frame (e.g. contact and proximity together); only
+/// the first counts.
+fn resolve_detonations(
+    mut detonations: MessageReader<Detonation>,
+    mut hits: HitResolver,
+    spatial: SpatialQuery,
+    relations: Res<FactionRelations>,
+    projectiles: Query<(), With<Projectile>>,
+    mut ships: DamageableShips,
+) {
+    let mut resolved = HashSet::new();
+    for detonation in detonations.read() {
+        if !resolved.insert(detonation.projectile) {
+            continue;
+        }
+        let explosive = detonation.explosive;
+        let filter =
+            SpatialQueryFilter::from_excluded_entities([detonation.projectile, detonation.owner]);
+        let caught = spatial.shape_intersections(
+            &Collider::sphere(explosive.radius),
+            detonation.position,
+            Quat::IDENTITY,
+            &filter,
+        );
+
+        for entity in caught {
+            let Ok(victim) = ships.get_mut(entity) else {
+                continue;
+            };
+            if !relations.can_damage(detonation.faction, victim.faction()) {
+                continue;
+            }
+            let offset = victim.transform.translation - detonation.position;
+            let to_hull = nearest_hull_point(victim.collider, victim.transform, detonation.position)
+                - detonation.position;
+            let hull_distance = to_hull.length();
+            let falloff = blast_falloff(hull_distance, explosive.radius, explosive.edge_falloff);
+            if falloff <= 0.0 {
+                continue;
+            }
+
+            // Inside the hull there's nothing to see past.
+            if explosive.occlusion
+                && let Ok(direction) = Dir3::new(to_hull)
+            {
+                let blocker = spatial.cast_ray_predicate(
+                    detonation.position,
+                    direction,
+                    hull_distance,
+                    true,
+                    &filter,
+                    &|hit| hit != entity && !projectiles.contains(hit),
+                );
+                if blocker.is_some() {
+                    continue;
+                }
+            }
+
+            hits.apply(
+                entity,
+                victim,
+                ShipHit {
+                    damage: detonation.damage.scaled(falloff),
+                    source_direction: -offset.normalize_or_zero(),
+                    impulse: offset.normalize_or_zero() * explosive.impulse * falloff,
+                    subsystem: None,
+                    attacker: detonation.owner,
+                },
+            );
+        }
+    }
+}
+
+/// A world with everything `fire_weapons` needs, for tests elsewhere in
+/// the crate that fire through it.
+#[cfg(test)]
+pub(crate) fn test_world() -> World {
+    let mut world = World::new();
+    world.init_resource::<Time>();
+    world.init_resource::<WeaponSettings>();
+    world.init_resource::<WeaponAssets>();
+    world.init_resource::<ProjectilePool>();
+    world
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn homing_slerp_reduces_angle_to_target() {
+        let current_dir = Vec3::NEG_Z;
+        let target_dir = Vec3::X;
+        let rot_current = Quat::from_rotation_arc(Vec3::NEG_Z, current_dir);
+        let rot_target = Quat::from_rotation_arc(Vec3::NEG_Z, target_dir);
+
+        let before_angle = current_dir.angle_between(target_dir);
+        let new_rot = rot_current.slerp(rot_target, 0.2);
+        let after_angle = (new_rot * Vec3::NEG_Z).angle_between(target_dir);
+
+        assert!(after_angle < before_angle);
+    }
+
+    #[test]
+    fn lifetime_timer_despawns_on_finish() {
+        let mut lifetime = ProjectileLifetime(Timer::from_seconds(1.0, TimerMode::Once));
+        assert!(!lifetime
+            .0
+            .tick(std::time::Duration::from_secs_f32(0.5))
+            .is_finished());
+        assert!(lifetime
+            .0
+            .tick(std::time::Duration::from_secs_f32(0.6))
+            .is_finished());
+    }
+
+    #[test]
+    fn lock_cone_rejects_targets_outside_angle_or_range() {
+        let cone = 30f32.to_radians();
+        let ahead = lock_cone_distance(Vec3::ZERO, Vec3::NEG_Z, Vec3::new(0.0, 0.0, -50.0), 150.0, cone);
+        let beside = lock_cone_distance(Vec3::ZERO, Vec3::NEG_Z, Vec3::new(50.0, 0.0, -10.0), 150.0, cone);
+        let far = lock_cone_distance(Vec3::ZERO, Vec3::NEG_Z, Vec3::new(0.0, 0.0, -200.0), 150.0, cone);
+        assert_eq!(ahead, Some(50.0));
+        assert_eq!(beside, None);
+        assert_eq!(far, None);
+    }
+
+    #[test]
+    fn missile_lock_needs_full_hold_time() {
+        let mut lock = MissileLock {
+            target: Some(Entity::PLACEHOLDER),
+            progress: 0.5,
+        };
+        assert!(!lock.is_locked(1.0));
+        assert_eq!(lock.fraction(1.0), 0.5);
+        lock.progress = 1.2;
+        assert!(lock.is_locked(1.0));
+        assert_eq!(lock.fraction(1.0), 1.0);
+        lock.reset();
+        assert_eq!(lock.fraction(1.0), 0.0);
+    }
+
+    #[test]
+    fn blast_falloff_is_linear_to_edge_and_zero_beyond() {
+        assert_eq!(blast_falloff(0.0, 8.0, 0.2), 1.0);
+        assert!((blast_falloff(4.0, 8.0, 0.2) - 0.6).abs() < 1e-5);
+        assert!((blast_falloff(8.0, 8.0, 0.2) - 0.2).abs() < 1e-5);
+        assert_eq!(blast_falloff(8.5, 8.0, 0.2), 0.0);
+    }
+
+    #[test]
+    fn blast_reaches_the_hull_of_a_large_ship() {
+        // Center 12 away, hull 2 away: well inside an 8 unit blast.
+        let hull = Collider::sphere(10.0);
+        let transform = Transform::from_xyz(12.0, 0.0, 0.0);
+        let nearest = nearest_hull_point(Some(&hull), &transform, Vec3::ZERO);
+        assert!(nearest.distance(Vec3::new(2.0, 0.0, 0.0)) < 1e-4);
+        assert!(blast_falloff(nearest.length(), 8.0, 0.2) > 0.2);
+        // Inside the hull counts as the blast center.
+        assert_eq!(nearest_hull_point(Some(&hull), &transform, transform.translation), transform.translation);
+        assert_eq!(nearest_hull_point(None, &transform, Vec3::ZERO), transform.translation);
+    }
+
+    #[test]
+    fn full_lock_survives_a_release_while_cooling_down() {
+        let mut world = test_world();
+        let mut loadout = WeaponLoadout::default();
+        // Just fired: the launcher is cycling.
+        let released = Trigger {
+            just_released: true,
+            ..default()
+        };
+        assert!(loadout.weapons[2].tick(0.0, released, 10.0, true).is_some());
+        let mut input = FireInput::default();
+        input.set(FireAction::Tertiary, true);
+        input.set(FireAction::Tertiary, false);
+        let target = world.spawn_empty().id();
+        let shooter = world
+            .spawn((
+                Transform::default(),
+                Faction::Player,
+                loadout,
+                input,
+                MissileLock {
+                    target: Some(target),
+                    progress: 5.0,
+                },
+            ))
+            .id();
+
+        world.run_system_once(fire_weapons).unwrap();
+        let mut projectiles = world.query::<&Projectile>();
+        assert_eq!(projectiles.iter(&world).count(), 0);
+        let lock = world.get::<MissileLock>(shooter).unwrap();
+        assert_eq!(lock.target, Some(target));
+        assert!(lock.is_locked(WeaponSettings::default().missile_lock_time));
+    }
+}
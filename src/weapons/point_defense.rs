@@ -0,0 +1,355 @@
+//! Autonomous point-defense turrets.
+//!
+//! A `PointDefense` lives on a child entity of the ship it protects; its
+//! local `Transform` is the mount, and the turret yaws and pitches within
+//! its arcs at a limited traverse speed. Each frame it picks the most
+//! urgent hostile it can bring to bear — missiles homing on its own ship,
+//! then any other hostile missile, then hostile ships — leads it, and fires
+//! its `Weapon` through the same `Weapon::tick` and projectile pool as
+//! every other gun. Shots are owned by the parent ship, so they never hit
+//! it and count as its fire for faction checks.
+
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+
+use avian3d::prelude::LinearVelocity;
+
+use super::{
+    FireAction, FireInput, FireMode, Homing, Projectile, ProjectileKind, ProjectilePool, Weapon,
+    WeaponAssets, WeaponSettings, spawn_projectile,
+};
+use crate::combat::Ship;
+use crate::faction::{Faction, FactionRelations};
+use crate::subsystems::{SubsystemKind, SubsystemTarget, Subsystems, subsystem_efficiency};
+
+#[derive(Component, Debug)]
+pub struct PointDefense {
+    pub weapon: Weapon,
+    pub range: f32,
+    /// Largest yaw, in radians either side of the mount's forward.
+    pub yaw_limit: f32,
+    /// Lowest and highest pitch, in radians; positive is up.
+    pub pitch_limits: (f32, f32),
+    /// Radians per second on each axis.
+    pub traverse_speed: f32,
+    /// Fires once the barrel is within this many radians of the lead point.
+    pub fire_tolerance: f32,
+    /// Whether hostile ships are engaged once no missiles are inbound.
+    pub engage_ships: bool,
+    pub target: Option<Entity>,
+    /// Local rotation the turret was mounted with; yaw and pitch are
+    /// applied on top of it.
+    mount: Option<Quat>,
+    yaw: f32,
+    pitch: f32,
+    input: FireInput,
+}
+
+impl PointDefense {
+    pub fn new(weapon: Weapon) -> Self {
+        Self {
+            weapon,
+            range: 60.0,
+            yaw_limit: PI,
+            pitch_limits: (-10f32.to_radians(), 85f32.to_radians()),
+            traverse_speed: 3.0,
+            fire_tolerance: 3f32.to_radians(),
+            engage_ships: true,
+            target: None,
+            mount: None,
+            yaw: 0.0,
+            pitch: 0.0,
+            input: FireInput::default(),
+        }
+    }
+
+    /// A dorsal flak turret: automatic turret rounds, full yaw, and the
+    /// default arcs and traverse.
+    pub fn flak() -> Self {
+        Self::new(Weapon::new(ProjectileKind::Turret, FireMode::Automatic))
+    }
+
+    pub fn with_arcs(mut self, yaw_limit: f32, pitch_limits: (f32, f32)) -> Self {
+        self.yaw_limit = yaw_limit;
+        self.pitch_limits = pitch_limits;
+        self
+    }
+
+    pub fn with_traverse_speed(mut self, traverse_speed: f32) -> Self {
+        self.traverse_speed = traverse_speed;
+        self
+    }
+
+    /// Clamps a mount-local aim (yaw, pitch) to the turret's arcs.
+    fn clamp_aim(&self, yaw: f32, pitch: f32) -> (f32, f32) {
+        (
+            yaw.clamp(-self.yaw_limit, self.yaw_limit),
+            pitch.clamp(self.pitch_limits.0, self.pitch_limits.1),
+        )
+    }
+
+    /// Yaw after one traverse `step` towards `desired`. A turret with full
+    /// yaw turns the short way across the seam behind the mount; a limited
+    /// one has to go round through its arc.
+    fn traverse_yaw(&self, desired: f32, step: f32) -> f32 {
+        let mut diff = desired - self.yaw;
+        if self.yaw_limit >= PI {
+            diff = (diff + PI).rem_euclid(TAU) - PI;
+        }
+        let yaw = self.yaw + diff.clamp(-step, step);
+        if yaw > PI {
+            yaw - TAU
+        } else if yaw < -PI {
+            yaw + TAU
+        } else {
+            yaw
+        }
+    }
+
+    /// Whether a mount-local direction lies inside the turret's arcs.
+    fn can_reach(&self, local_direction: Vec3) -> bool {
+        let (yaw, pitch) = yaw_pitch(local_direction);
+        self.clamp_aim(yaw, pitch) == (yaw, pitch)
+    }
+}
+
+/// Yaw about +Y and pitch about +X that turn -Z (forward) onto `direction`.
+fn yaw_pitch(direction: Vec3) -> (f32, f32) {
+    let direction = direction.normalize_or_zero();
+    (
+        f32::atan2(-direction.x, -direction.z),
+        direction.y.clamp(-1.0, 1.0).asin(),
+    )
+}
+
+fn aim_rotation(yaw: f32, pitch: f32) -> Quat {
+    Quat::from_rotation_y(yaw) * Quat::from_rotation_x(pitch)
+}
+
+/// Where to aim a shot of `speed` fired from `origin` so that it meets a
+/// target at `target` moving with constant `velocity`. `None` if the
+/// target outruns the shot.
+pub fn intercept_point(origin: Vec3, target: Vec3, velocity: Vec3, speed: f32) -> Option<Vec3> {
+    // |offset + velocity * t| = speed * t, solved for the smallest t > 0.
+    let offset = target - origin;
+    let a = velocity.length_squared() - speed * speed;
+    let b = 2.0 * offset.dot(velocity);
+    let c = offset.length_squared();
+    let t = if a.abs() < 1e-6 {
+        if b >= 0.0 {
+            return None;
+        }
+        -c / b
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let (t1, t2) = ((-b - root) / (2.0 * a), (-b + root) / (2.0 * a));
+        match (t1 > 0.0, t2 > 0.0) {
+            (true, true) => t1.min(t2),
+            (true, false) => t1,
+            (false, true) => t2,
+            (false, false) => return None,
+        }
+    };
+    Some(target + velocity * t)
+}
+
+/// Engagement priority; lower is more urgent.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Threat {
+    InboundMissile,
+    Missile,
+    Ship,
+}
+
+/// Picks targets, traverses towards the lead point, and fires.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(super) fn operate_point_defense(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<WeaponSettings>,
+    assets: Res<WeaponAssets>,
+    relations: Res<FactionRelations>,
+    mut pool: ResMut<ProjectilePool>,
+    mut turrets: Query<(&ChildOf, &mut Transform, &mut PointDefense)>,
+    parents: Query<
+        (
+            &Transform,
+            &Faction,
+            Option<&Subsystems>,
+            Option<&SubsystemTarget>,
+        ),
+        Without<PointDefense>,
+    >,
+    missiles: Query<(Entity, &Transform, &LinearVelocity, &Projectile, &Homing), Without<PointDefense>>,
+    ships: Query<
+        (Entity, &Transform, Option<&LinearVelocity>, Option<&Faction>),
+        (With<Ship>, Without<PointDefense>),
+    >,
+) {
+    let dt = time.delta_secs();
+    for (child_of, mut transform, mut turret) in &mut turrets {
+        let parent = child_of.parent();
+        let Ok((parent_transform, faction, subsystems, subsystem_target)) = parents.get(parent)
+        else {
+            continue;
+        };
+        let mount = *turret.mount.get_or_insert(transform.rotation);
+        let origin = parent_transform.transform_point(transform.translation);
+        let mount_rotation = parent_transform.rotation * mount;
+        let stats = settings.stats(turret.weapon.kind);
+
+        // Every hostile in range that the arcs can reach, with where to aim.
+        let lead = |position: Vec3, velocity: Vec3| {
+            if position.distance(origin) > turret.range {
+                return None;
+            }
+            let aim = intercept_point(origin, position, velocity, stats.speed)?;
+            turret
+                .can_reach(mount_rotation.inverse() * (aim - origin))
+                .then_some(aim)
+        };
+        let missile_threats = missiles
+            .iter()
+            .filter(|(.., projectile, _)| {
+                projectile.owner != parent && relations.is_hostile(*faction, projectile.faction)
+            })
+            .filter_map(|(entity, missile_transform, velocity, _, homing)| {
+                let threat = if homing.target == parent {
+                    Threat::InboundMissile
+                } else {
+                    Threat::Missile
+                };
+                let aim = lead(missile_transform.translation, velocity.0)?;
+                Some((threat, entity, missile_transform.translation, aim))
+            });
+        let ship_threats = ships
+            .iter()
+            .filter(|_| turret.engage_ships)
+            .filter(|(entity, _, _, ship_faction)| {
+                *entity != parent
+                    && relations.is_hostile(*faction, ship_faction.copied().unwrap_or_default())
+            })
+            .filter_map(|(entity, ship_transform, velocity, _)| {
+                let velocity = velocity.map_or(Vec3::ZERO, |velocity| velocity.0);
+                let aim = lead(ship_transform.translation, velocity)?;
+                Some((Threat::Ship, entity, ship_transform.translation, aim))
+            });
+        let threats: Vec<_> = missile_threats.chain(ship_threats).collect();
+
+        // Most urgent tier first, then closest; the current target is kept
+        // while nothing more urgent shows up, so the turret doesn't flick
+        // between two equally close threats.
+        let best = threats.iter().min_by(|a, b| {
+            a.0.cmp(&b.0)
+                .then(a.2.distance(origin).total_cmp(&b.2.distance(origin)))
+        });
+        let current = threats
+            .iter()
+            .find(|threat| Some(threat.1) == turret.target);
+        let engaged = match (current, best) {
+            (Some(current), Some(best)) if current.0 <= best.0 => Some(current),
+            _ => best,
+        };
+        let (target, aim) = match engaged {
+            Some(&(_, entity, _, aim)) => (Some(entity), Some(aim)),
+            None => (None, None),
+        };
+        turret.target = target;
+
+        // Traverse towards the lead point, or back to rest when idle.
+        let (desired_yaw, desired_pitch) = aim.map_or((0.0, 0.0), |aim| {
+            let (yaw, pitch) = yaw_pitch(mount_rotation.inverse() * (aim - origin));
+            turret.clamp_aim(yaw, pitch)
+        });
+        let step = turret.traverse_speed * dt;
+        turret.yaw = turret.traverse_yaw(desired_yaw, step);
+        turret.pitch += (desired_pitch - turret.pitch).clamp(-step, step);
+        transform.rotation = mount * aim_rotation(turret.yaw, turret.pitch);
+
+        let world_rotation = parent_transform.rotation * transform.rotation;
+        let forward = world_rotation * Vec3::NEG_Z;
+        let on_target = aim.is_some_and(|aim| {
+            forward.angle_between(aim - origin) <= turret.fire_tolerance
+        });
+
+        let turret = &mut *turret;
+        turret.input.set(FireAction::Primary, on_target);
+        let trigger = turret.input.trigger(FireAction::Primary);
+        let cooldown =
+            stats.cooldown / subsystem_efficiency(subsystems, SubsystemKind::Weapons);
+        let Some(scale) = turret.weapon.tick(dt, trigger, cooldown, true) else {
+            continue;
+        };
+        spawn_projectile(
+            &mut commands,
+            &mut pool,
+            &assets,
+            turret.weapon.kind,
+            origin + world_rotation * turret.weapon.offset,
+            world_rotation,
+            forward * stats.speed * scale.speed,
+            Projectile {
+                damage: stats.damage.scaled(scale.damage),
+                owner: parent,
+                faction: *faction,
+                mass: stats.mass,
+                subsystem: subsystem_target.and_then(|target| target.0),
+            },
+            stats.lifetime,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intercept_leads_a_crossing_target() {
+        let aim = intercept_point(Vec3::ZERO, Vec3::new(0.0, 0.0, -100.0), Vec3::X * 10.0, 100.0)
+            .unwrap();
+        assert!(aim.x > 0.0);
+        // Shot and target arrive together.
+        let t = aim.length() / 100.0;
+        let target_at_t = Vec3::new(0.0, 0.0, -100.0) + Vec3::X * 10.0 * t;
+        assert!(aim.distance(target_at_t) < 1e-3);
+    }
+
+    #[test]
+    fn intercept_fails_against_faster_receding_target() {
+        let aim = intercept_point(Vec3::ZERO, Vec3::new(0.0, 0.0, -50.0), Vec3::NEG_Z * 200.0, 100.0);
+        assert!(aim.is_none());
+    }
+
+    #[test]
+    fn arcs_limit_reachable_directions() {
+        let turret = PointDefense::flak().with_arcs(90f32.to_radians(), (0.0, 80f32.to_radians()));
+        assert!(turret.can_reach(Vec3::new(-1.0, 0.5, -1.0)));
+        // Behind, and below the deck.
+        assert!(!turret.can_reach(Vec3::Z));
+        assert!(!turret.can_reach(Vec3::new(0.0, -0.5, -1.0)));
+        let (yaw, pitch) = yaw_pitch(aim_rotation(0.4, 0.2) * Vec3::NEG_Z);
+        assert!((yaw - 0.4).abs() < 1e-4 && (pitch - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn full_yaw_traverses_the_short_way_across_the_seam() {
+        let mut turret = PointDefense::flak();
+        turret.yaw = PI - 0.1;
+        // Just across the seam: 0.2 rad away, not 2π - 0.2.
+        let yaw = turret.traverse_yaw(-PI + 0.1, 0.5);
+        assert!((yaw - (-PI + 0.1)).abs() < 1e-4);
+        turret.yaw = PI - 0.1;
+        let yaw = turret.traverse_yaw(-PI + 0.1, 0.05);
+        assert!((yaw - (PI - 0.05)).abs() < 1e-4);
+        // A limited arc can't cut through its dead zone.
+        let mut turret = turret.with_arcs(3.0, (0.0, 1.0));
+        turret.yaw = 2.9;
+        assert!((turret.traverse_yaw(-2.9, 0.5) - 2.4).abs() < 1e-4);
+    }
+}
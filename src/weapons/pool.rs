@@ -0,0 +1,186 @@
+//! Projectile pooling: every projectile entity is allocated once, up front,
+//! and recycled instead of spawned per shot and despawned per hit.
+//!
+//! An idle pooled entity keeps its mesh, material and collider but is
+//! hidden, has `RigidBodyDisabled`/`ColliderDisabled`, and carries none of
+//! the gameplay components (`Projectile`, `ProjectileLifetime`, `Homing`,
+//! `Explosive`), so no weapon system ever sees it. Taking one out of the
+//! pool re-adds those; releasing it strips them again. If a pool runs dry
+//! it grows rather than dropping the shot, and reports that as pressure.
+
+use bevy::platform::collections::HashMap;
+use bevy::prelude::*;
+use metrics::{counter, gauge};
+
+use avian3d::prelude::{
+    Collider, ColliderDisabled, CollisionEventsEnabled, LinearVelocity, RigidBody,
+    RigidBodyDisabled, Sensor,
+};
+
+use super::{
+    Explosive, Homing, Mine, Projectile, ProjectileHealth, ProjectileLifetime, WeaponAssets,
+    WeaponSettings,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ProjectileKind {
+    Turret,
+    Laser,
+    Missile,
+    Mine,
+}
+
+impl ProjectileKind {
+    pub const ALL: [ProjectileKind; 4] = [
+        ProjectileKind::Turret,
+        ProjectileKind::Laser,
+        ProjectileKind::Missile,
+        ProjectileKind::Mine,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            ProjectileKind::Turret => "turret",
+            ProjectileKind::Laser => "laser",
+            ProjectileKind::Missile => "missile",
+            ProjectileKind::Mine => "mine",
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ProjectilePool {
+    free: HashMap<ProjectileKind, Vec<Entity>>,
+    /// Every pooled entity currently in flight, and which pool it returns to.
+    live: HashMap<Entity, ProjectileKind>,
+}
+
+impl ProjectilePool {
+    /// Hands out an idle entity of `kind`, spawning a new one if the pool is
+    /// empty. The caller is responsible for activating it.
+    pub(super) fn acquire(
+        &mut self,
+        commands: &mut Commands,
+        assets: &WeaponAssets,
+        kind: ProjectileKind,
+    ) -> Entity {
+        let entity = match self.free.get_mut(&kind).and_then(Vec::pop) {
+            Some(entity) => entity,
+            None => {
+                counter!("weapons.pool.grown", "kind" => kind.label()).increment(1);
+                spawn_idle(commands, assets, kind)
+            }
+        };
+        self.live.insert(entity, kind);
+        entity
+    }
+
+    /// Returns a live projectile to its pool. Safe to call more than once
+    /// for the same entity in a frame (e.g. a hit and a lifetime expiry
+    /// together); only the first call does anything.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity) {
+        let Some(kind) = self.live.remove(&entity) else {
+            return;
+        };
+        commands
+            .entity(entity)
+            .remove::<(
+                Projectile,
+                ProjectileLifetime,
+                ProjectileHealth,
+                Homing,
+                Explosive,
+                Mine,
+            )>()
+            .insert((
+                Visibility::Hidden,
+                LinearVelocity::ZERO,
+                RigidBodyDisabled,
+                ColliderDisabled,
+            ));
+        self.free.entry(kind).or_default().push(entity);
+    }
+
+    pub fn live_count(&self, kind: ProjectileKind) -> usize {
+        self.live.values().filter(|live| **live == kind).count()
+    }
+
+    pub fn free_count(&self, kind: ProjectileKind) -> usize {
+        self.free.get(&kind).map_or(0, Vec::len)
+    }
+}
+
+fn spawn_idle(commands: &mut Commands, assets: &WeaponAssets, kind: ProjectileKind) -> Entity {
+    // Missiles get a fatter collider than their mesh so that they're a
+    // fair target for turret fire and point defense.
+    let (mesh, material, radius) = match kind {
+        ProjectileKind::Turret => (&assets.turret_mesh, &assets.turret_material, 0.12),
+        ProjectileKind::Laser => (&assets.laser_mesh, &assets.laser_material, 0.12),
+        ProjectileKind::Missile => (&assets.missile_mesh, &assets.missile_material, 0.4),
+        ProjectileKind::Mine => (&assets.mine_mesh, &assets.mine_material, 0.5),
+    };
+    commands
+        .spawn((
+            Name::new(format!("Pooled {} projectile", kind.label())),
+            Mesh3d(mesh.clone()),
+            MeshMaterial3d(material.clone()),
+            Transform::default(),
+            Visibility::Hidden,
+            RigidBody::Kinematic,
+            RigidBodyDisabled,
+            Collider::sphere(radius),
+            ColliderDisabled,
+            Sensor,
+            CollisionEventsEnabled,
+            LinearVelocity::ZERO,
+        ))
+        .id()
+}
+
+pub(super) fn fill_projectile_pool(
+    mut commands: Commands,
+    settings: Res<WeaponSettings>,
+    assets: Res<WeaponAssets>,
+    mut pool: ResMut<ProjectilePool>,
+) {
+    for kind in ProjectileKind::ALL {
+        let size = match kind {
+            ProjectileKind::Turret => settings.turret_pool_size,
+            ProjectileKind::Laser => settings.laser_pool_size,
+            ProjectileKind::Missile => settings.missile_pool_size,
+            ProjectileKind::Mine => settings.mine_pool_size,
+        };
+        let free = pool.free.entry(kind).or_default();
+        for _ in 0..size {
+            free.push(spawn_idle(&mut commands, &assets, kind));
+        }
+    }
+}
+
+pub(super) fn report_pool_pressure(pool: Res<ProjectilePool>) {
+    for kind in ProjectileKind::ALL {
+        gauge!("weapons.pool.live", "kind" => kind.label()).set(pool.live_count(kind) as f64);
+        gauge!("weapons.pool.free", "kind" => kind.label()).set(pool.free_count(kind) as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release_is_idempotent() {
+        let mut world = World::new();
+        let entity = world.spawn_empty().id();
+        let mut pool = ProjectilePool::default();
+        pool.live.insert(entity, ProjectileKind::Turret);
+
+        let mut queue = bevy::ecs::world::CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        pool.release(&mut commands, entity);
+        pool.release(&mut commands, entity);
+
+        assert_eq!(pool.live_count(ProjectileKind::Turret), 0);
+        assert_eq!(pool.free_count(ProjectileKind::Turret), 1);
+    }
+}